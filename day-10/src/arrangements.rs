@@ -0,0 +1,224 @@
+use super::joltage_chain;
+
+/// Lazily walks every valid arrangement in lexicographic order of the adapters chosen, starting
+/// with the one that uses every adapter. Each item lists the adapters used, without the outlet or
+/// the device.
+pub struct Arrangements {
+    chain: Vec<u32>,
+    max_step: u32,
+    path: Vec<usize>,
+    started: bool,
+    done: bool
+}
+
+impl Arrangements {
+    pub fn new(adapters: &[u32], max_step: u32) -> Self {
+        Arrangements { chain: joltage_chain(adapters, max_step), max_step, path: vec![0], started: false, done: false }
+    }
+
+    fn can_step(&self, from: usize, to: usize) -> bool {
+        to < self.chain.len() && self.chain[to] - self.chain[from] <= self.max_step
+    }
+
+    /// Replaces the most recent choice that has an untried alternative with that alternative.
+    fn backtrack(&mut self) -> bool {
+        while self.path.len() > 1 {
+            let choice = self.path.pop().unwrap();
+            let previous = *self.path.last().unwrap();
+
+            if self.can_step(previous, choice + 1) {
+                self.path.push(choice + 1);
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+impl Iterator for Arrangements {
+    type Item = Vec<u32>;
+
+    fn next(&mut self) -> Option<Vec<u32>> {
+        if self.done || (self.started && !self.backtrack()) {
+            self.done = true;
+            return None;
+        }
+
+        self.started = true;
+        let end = self.chain.len() - 1;
+
+        loop {
+            let last = *self.path.last().unwrap();
+            if last == end {
+                return Some(self.path[1..self.path.len() - 1].iter().map(|i| self.chain[*i]).collect());
+            }
+
+            if self.can_step(last, last + 1) {
+                self.path.push(last + 1);
+            } else if !self.backtrack() {
+                self.done = true;
+                return None;
+            }
+        }
+    }
+}
+
+/// A xorshift64* generator, good enough for sampling and reproducible from a seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `0..bound`, rejecting draws that would bias the low values.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - (u64::MAX % bound);
+        loop {
+            let draw = self.next_u64();
+            if draw < zone { return draw % bound; }
+        }
+    }
+}
+
+/// Draws `count` arrangements uniformly at random. Each step picks the next adapter weighted by
+/// how many arrangements continue from it, so every complete chain is equally likely.
+/// Returns `None` if there are no arrangements or too many to count in a u64.
+pub fn sample(adapters: &[u32], max_step: u32, count: usize, seed: u64) -> Option<Vec<Vec<u32>>> {
+    let chain = joltage_chain(adapters, max_step);
+    let end = chain.len() - 1;
+    let mut ways_from = vec![0u64; chain.len()];
+    ways_from[end] = 1;
+
+    for i in (0..end).rev() {
+        for j in (i + 1..=end).take_while(|j| chain[*j] - chain[i] <= max_step) {
+            ways_from[i] = ways_from[i].checked_add(ways_from[j])?;
+        }
+    }
+
+    if ways_from[0] == 0 {
+        return None;
+    }
+
+    let mut rng = Rng::new(seed);
+    let samples = (0..count)
+        .map(|_| {
+            let mut arrangement = Vec::new();
+            let mut position = 0;
+
+            while position != end {
+                let mut pick = rng.below(ways_from[position]);
+                position = (position + 1..=end)
+                    .find(|j| if pick < ways_from[*j] { true } else { pick -= ways_from[*j]; false })
+                    .unwrap();
+
+                if position != end { arrangement.push(chain[position]); }
+            }
+
+            arrangement
+        })
+        .collect();
+
+    Some(samples)
+}
+
+/// The arrangement with the fewest adapters, always jumping as far as possible.
+pub fn minimal(adapters: &[u32], max_step: u32) -> Option<Vec<u32>> {
+    let chain = joltage_chain(adapters, max_step);
+    if chain.windows(2).any(|pair| pair[1] - pair[0] > max_step) {
+        return None;
+    }
+
+    let end = chain.len() - 1;
+    let mut arrangement = Vec::new();
+    let mut position = 0;
+
+    while position != end {
+        position = (position + 1..=end).take_while(|j| chain[*j] - chain[position] <= max_step).last().unwrap();
+        if position != end { arrangement.push(chain[position]); }
+    }
+
+    Some(arrangement)
+}
+
+/// The arrangement with the most adapters, which is every adapter if any arrangement exists.
+pub fn maximal(adapters: &[u32], max_step: u32) -> Option<Vec<u32>> {
+    Arrangements::new(adapters, max_step).next()
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum AdapterRole {
+    /// Leaving this adapter out would open a gap of the given size between its neighbours.
+    Mandatory { gap_without: u32 },
+    Optional
+}
+
+/// Whether each adapter appears in every arrangement. Since the joltages are sorted, an adapter
+/// can be skipped exactly when its neighbours are within `max_step` of each other.
+pub fn roles(adapters: &[u32], max_step: u32) -> Vec<(u32, AdapterRole)> {
+    let chain = joltage_chain(adapters, max_step);
+
+    chain.windows(3)
+        .map(|w| {
+            let gap_without = w[2] - w[0];
+            let role = if gap_without > max_step { AdapterRole::Mandatory { gap_without } } else { AdapterRole::Optional };
+            (w[1], role)
+        })
+        .collect()
+}
+
+#[test]
+fn test_enumerate_arrangements() {
+    let adapters = super::sorted_adapters("example2.txt");
+    let arrangements: Vec<Vec<u32>> = Arrangements::new(&adapters, 3).collect();
+
+    assert_eq!(arrangements.len(), 8);
+    assert_eq!(arrangements[0], adapters);
+    assert_eq!(arrangements[7], vec![1, 4, 7, 10, 12, 15, 16, 19]);
+    assert_eq!(Arrangements::new(&[1, 5], 3).count(), 0);
+    assert_eq!(Arrangements::new(&super::sorted_adapters("example.txt"), 3).count(), 19208);
+}
+
+#[test]
+fn test_minimal_and_maximal() {
+    let adapters = super::sorted_adapters("example2.txt");
+
+    assert_eq!(minimal(&adapters, 3), Some(vec![1, 4, 7, 10, 12, 15, 16, 19]));
+    assert_eq!(maximal(&adapters, 3), Some(adapters));
+    assert_eq!(minimal(&[1, 5], 3), None);
+    assert_eq!(maximal(&[1, 5], 3), None);
+}
+
+#[test]
+fn test_sampling() {
+    let adapters = super::sorted_adapters("example2.txt");
+    let all: Vec<Vec<u32>> = Arrangements::new(&adapters, 3).collect();
+    let samples = sample(&adapters, 3, 400, 2020).unwrap();
+
+    assert!(samples.iter().all(|s| all.contains(s)));
+    assert!(all.iter().all(|a| samples.contains(a)));
+    assert_eq!(samples, sample(&adapters, 3, 400, 2020).unwrap());
+    assert_eq!(sample(&[1, 5], 3, 1, 2020), None);
+}
+
+#[test]
+fn test_roles() {
+    let roles = roles(&[1, 4, 5, 6, 7, 10], 3);
+
+    assert_eq!(roles, vec![
+        (1, AdapterRole::Mandatory { gap_without: 4 }),
+        (4, AdapterRole::Mandatory { gap_without: 4 }),
+        (5, AdapterRole::Optional),
+        (6, AdapterRole::Optional),
+        (7, AdapterRole::Mandatory { gap_without: 4 }),
+        (10, AdapterRole::Mandatory { gap_without: 6 })
+    ]);
+}
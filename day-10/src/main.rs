@@ -1,3 +1,11 @@
+mod arrangements;
+
+use std::collections::BTreeMap;
+
+use arrangements::AdapterRole;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
 fn sorted_adapters(path: &str) -> Vec<u32> {
     let mut input: Vec<u32> = std::fs::read_to_string(path)
         .unwrap()
@@ -5,55 +13,73 @@ fn sorted_adapters(path: &str) -> Vec<u32> {
         .map(|x| x.parse::<u32>().unwrap())
         .collect();
 
-    input.sort();
+    input.sort_unstable();
 
     input
 }
 
-fn build_distribution(input: &Vec<u32>) -> (usize, usize) {
-    let mut ones = 0;
-    let mut threes = 0;
+/// The outlet, every adapter and the device, which is rated `max_step` above the largest adapter.
+fn joltage_chain(adapters: &[u32], max_step: u32) -> Vec<u32> {
+    let device = adapters.last().copied().unwrap_or(0) + max_step;
+
+    std::iter::once(0)
+        .chain(adapters.iter().copied())
+        .chain(std::iter::once(device))
+        .collect()
+}
 
-    input.iter().zip([0].iter().chain(input.iter()))
-        .for_each(|(larger, smaller)| {
-            match larger - smaller {
-                1 => ones += 1,
-                3 => threes +=1,
-                _ => ()
-            }
-        });
+/// How many times each joltage difference occurs when every adapter is used.
+fn difference_distribution(adapters: &[u32], max_step: u32) -> BTreeMap<u32, usize> {
+    let chain = joltage_chain(adapters, max_step);
+    let mut distribution = BTreeMap::new();
 
-    (ones, threes + 1)
+    for pair in chain.windows(2) {
+        *distribution.entry(pair[1] - pair[0]).or_insert(0) += 1;
+    }
+
+    distribution
 }
 
-// Could be made more efficient with a cache
-fn ways(run: u64) -> u64 {
-    match run {
-        0 | 1 | 2 => 1,
-        3 => 2,
-        r => ways(r - 3) + ways(r - 2) + ways(r - 1)
+fn build_distribution(input: &[u32]) -> (usize, usize) {
+    let distribution = difference_distribution(input, 3);
+
+    (distribution.get(&1).copied().unwrap_or(0), distribution.get(&3).copied().unwrap_or(0))
+}
+
+/// Counts arrangements with `ways[i]` being the number of ways to reach the i-th joltage, summing
+/// over every earlier joltage within `max_step` of it. Returns `None` if the count overflows.
+fn count_arrangements_u64(adapters: &[u32], max_step: u32) -> Option<u64> {
+    let chain = joltage_chain(adapters, max_step);
+    let mut ways = vec![0u64; chain.len()];
+    ways[0] = 1;
+
+    for i in 1..chain.len() {
+        for j in (0..i).rev().take_while(|j| chain[i] - chain[*j] <= max_step) {
+            ways[i] = ways[i].checked_add(ways[j])?;
+        }
     }
+
+    ways.last().copied()
 }
 
-fn possibilities_for(adapters: &Vec<u32>) -> u64 {
-    // Build a diff list
-    let mut diffs: Vec<u32> = adapters.iter().zip([0].iter().chain(adapters.iter()))
-        .map(|(larger, smaller)| larger - smaller)
-        .collect();
+fn count_arrangements(adapters: &[u32], max_step: u32) -> BigUint {
+    if let Some(count) = count_arrangements_u64(adapters, max_step) {
+        return BigUint::from(count);
+    }
 
-    diffs.push(3);
+    let chain = joltage_chain(adapters, max_step);
+    let mut ways = vec![BigUint::zero(); chain.len()];
+    ways[0] = BigUint::one();
 
-    // Find all of the runs in it
-    let (runs, _) = diffs.iter().fold((Vec::new(), 1 as u64), |(mut runs, current_run), diff| {
-        match diff {
-            1 => (runs, current_run + 1),
-            3 => { runs.push(current_run); (runs, 1) },
-            _ => panic!()
+    for i in 1..chain.len() {
+        let mut total = BigUint::zero();
+        for j in (0..i).rev().take_while(|j| chain[i] - chain[*j] <= max_step) {
+            total += &ways[j];
         }
-    });
+        ways[i] = total;
+    }
 
-    // Each run has some number of different combos; multiply them all
-    runs.iter().map(|r| ways(*r)).product()
+    ways.pop().unwrap()
 }
 
 fn main() {
@@ -64,7 +90,27 @@ fn main() {
     println!("Part 1: {}", ones * threes);
 
     // Part two
-    println!("Part 2: {}", possibilities_for(&adapters));
+    println!("Part 2: {}", count_arrangements(&adapters, 3));
+
+    for (difference, count) in difference_distribution(&adapters, 3) {
+        println!("  {} jolt differences: {}", difference, count);
+    }
+
+    let mandatory: Vec<u32> = arrangements::roles(&adapters, 3).into_iter()
+        .filter(|(_, role)| *role != AdapterRole::Optional)
+        .map(|(joltage, _)| joltage)
+        .collect();
+
+    println!("  Mandatory adapters ({}): {:?}", mandatory.len(), mandatory);
+    println!("  Shortest chain: {} adapters", arrangements::minimal(&adapters, 3).unwrap().len());
+    println!("  Longest chain: {} adapters", arrangements::maximal(&adapters, 3).unwrap().len());
+
+    let sample_lengths: Vec<usize> = arrangements::sample(&adapters, 3, 5, 2020).unwrap()
+        .iter()
+        .map(|sample| sample.len())
+        .collect();
+
+    println!("  Random chain lengths: {:?}", sample_lengths);
 }
 
 #[test]
@@ -83,10 +129,33 @@ fn test_part_one() {
 #[test]
 fn test_part_two() {
     let adapters = sorted_adapters("example2.txt");
-    let possibilities = possibilities_for(&adapters);
-    assert_eq!(possibilities, 8);
+    let possibilities = count_arrangements(&adapters, 3);
+    assert_eq!(possibilities, BigUint::from(8u32));
 
     let adapters = sorted_adapters("example.txt");
-    let possibilities = possibilities_for(&adapters);
-    assert_eq!(possibilities, 19208);
+    let possibilities = count_arrangements(&adapters, 3);
+    assert_eq!(possibilities, BigUint::from(19208u32));
+}
+
+#[test]
+fn test_arbitrary_gaps() {
+    // Gaps of two are allowed and simply leave fewer adapters to skip
+    assert_eq!(count_arrangements_u64(&[2, 4, 5, 7], 3), Some(3));
+    assert_eq!(count_arrangements_u64(&[2, 4, 5, 7], 2), Some(1));
+    assert_eq!(count_arrangements_u64(&[1, 5], 3), Some(0));
+
+    let adapters = sorted_adapters("example.txt");
+    assert_eq!(count_arrangements_u64(&adapters, 1), Some(0));
+    assert_eq!(count_arrangements_u64(&adapters, 4), Some(4313088));
+
+    let distribution = difference_distribution(&[2, 4, 5, 7], 3);
+    assert_eq!(distribution.into_iter().collect::<Vec<(u32, usize)>>(), vec![(1, 1), (2, 3), (3, 1)]);
+}
+
+#[test]
+fn test_big_arrangements() {
+    let adapters: Vec<u32> = (1..=100).collect();
+
+    assert_eq!(count_arrangements_u64(&adapters, 3), None);
+    assert_eq!(count_arrangements(&adapters, 3).to_string(), "180396380815100901214157639");
 }
@@ -1,4 +1,15 @@
+mod neighbors;
+mod render;
+mod rules;
+
 use std::{fmt::Display};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::Path;
+use std::time::Duration;
+
+use neighbors::NeighborTable;
+use rules::{Neighborhood, Rule, DIRECTIONS};
 
 #[derive(Eq, PartialEq, Copy, Clone)]
 enum Seat {
@@ -7,12 +18,6 @@ enum Seat {
     Floor
 }
 
-#[derive(Copy, Clone)]
-enum AdjacencyMethod {
-    Proximity,
-    Sight
-}
-
 impl Display for Seat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let out = match self {
@@ -35,11 +40,33 @@ impl From<char> for Seat {
     }
 }
 
+#[derive(Debug, Eq, PartialEq)]
+enum Outcome {
+    Stable { after: usize },
+    Cycle { start: usize, period: usize },
+    Unfinished { iterations: usize }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Stable { after } => write!(f, "stable after {} iterations", after),
+            Outcome::Cycle { start, period } => write!(f, "cycle of length {} starting at iteration {}", period, start),
+            Outcome::Unfinished { iterations } => write!(f, "still changing after {} iterations", iterations)
+        }
+    }
+}
+
 struct World {
     seats: Vec<Seat>,
+    next: Vec<Seat>,
     iterations: usize,
     length: usize,
-    height: usize
+    height: usize,
+    table: Option<NeighborTable>,
+    frontier: Vec<u32>,
+    in_frontier: Vec<bool>,
+    changed: Vec<u32>
 }
 
 impl Display for World {
@@ -49,7 +76,7 @@ impl Display for World {
                 write!(f, "{}", seat)?;
             }
 
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
 
         Ok(())
@@ -67,168 +94,321 @@ impl World {
 
         let seats: Vec<Seat> = input
             .lines()
-            .map(|l| l.chars().map(|c| Seat::from(c)).collect::<Vec<Seat>>())
-            .flatten()
+            .flat_map(|l| l.chars().map(Seat::from))
             .collect();         
             
-        let length = input.find(|c| c == '\n').unwrap();
+        let length = input.find('\n').unwrap();
         let height = seats.len() / length;
 
-        World { seats, iterations: 0, length, height }
+        let next = seats.clone();
+        let in_frontier = vec![false; seats.len()];
+
+        World { seats, next, iterations: 0, length, height, table: None, frontier: Vec::new(), in_frontier, changed: Vec::new() }
     }
 
-    fn adjacent_indeces_by_proximity(&self, index: usize) -> Vec<usize> {
+    fn offset_index(&self, index: usize, (x, y): (i32, i32)) -> Option<usize> {
         let row = index / self.length;
         let col = index % self.length;
 
-        let translations: [(i32, i32); 8] = [
-            (-1, -1), (0, -1), (1, -1),
-            (-1,  0),          (1,  0),
-            (-1,  1), (0,  1), (1,  1)
-        ];
-
-        translations.iter()
-            .filter(|(x, y)| 
-                falls_within(row, *y, 0, self.height) && 
-                falls_within(col, *x, 0, self.length)
-            )
-            .map(|(x, y)| (index as i32+ (y * self.length as i32) + *x) as usize)
-            .collect()
+        if falls_within(row, y, 0, self.height) && falls_within(col, x, 0, self.length) {
+            Some((index as i32 + (y * self.length as i32) + x) as usize)
+        } else {
+            None
+        }
     }
 
     fn adjacent_indeces_by_sight(&self, index: usize) -> Vec<usize> {
-        let row = index / self.length;
-        let col = index % self.length;
+        let mut indeces_out = Vec::new();
 
-        let translations: [(i32, i32); 8] = [
-            (-1, -1), (0, -1), (1, -1),
-            (-1,  0),          (1,  0),
-            (-1,  1), (0,  1), (1,  1)
-        ];
+        for (shift_x, shift_y) in DIRECTIONS.iter() {
+            let mut current_index = index;
 
-        let mut indeces_out = Vec::new();
+            // Walk until we hit a seat or the edge of the world
+            while let Some(next_index) = self.offset_index(current_index, (*shift_x, *shift_y)) {
+                current_index = next_index;
 
-        for (shift_x, shift_y) in translations.iter() {
-            let mut x = col;
-            let mut y = row;
-
-            loop {
-                // Break if the shift cannot be performed
-                if !falls_within(x, *shift_x, 0, self.length) { break; }
-                if !falls_within(y, *shift_y, 0, self.height) { break; }
-                
-                // Perform the shift
-                x = (x as i32 + shift_x) as usize;
-                y = (y as i32 + shift_y) as usize;
-
-                // If we hit a seat, return that
-                let current_index = y * self.length + x;
-                match self.seats[current_index] {
-                    Seat::Occupied | Seat::Vacant => {
-                        indeces_out.push(current_index);
-                        break;
-                    },
-                    Seat::Floor => ()
+                if self.seats[current_index] != Seat::Floor {
+                    indeces_out.push(current_index);
+                    break;
                 }
             }
-
         }
 
         indeces_out
     }
 
-    fn next_state_for(&self, index: usize, method: AdjacencyMethod) -> Seat {
-        if self.seats[index] == Seat::Floor { return Seat::Floor; }
+    fn neighbors(&self, index: usize, neighborhood: &Neighborhood) -> Vec<usize> {
+        if self.seats[index] == Seat::Floor { return Vec::new(); }
 
-        let adjacent_tiles = match method {
-            AdjacencyMethod::Proximity => self.adjacent_indeces_by_proximity(index),
-            AdjacencyMethod::Sight => self.adjacent_indeces_by_sight(index)
+        let candidates = match neighborhood {
+            Neighborhood::Sight => self.adjacent_indeces_by_sight(index),
+            other => other.offsets()
+                .into_iter()
+                .filter_map(|offset| self.offset_index(index, offset))
+                .collect()
         };
 
-        let adjacent_count = adjacent_tiles
+        // Floor is never occupied, so it is left out of the table entirely
+        candidates.into_iter().filter(|i| self.seats[*i] != Seat::Floor).collect()
+    }
+
+    /// Builds the neighbor table for this neighborhood if needed, evaluating every seat next step.
+    fn prepare(&mut self, neighborhood: &Neighborhood) {
+        if self.table.as_ref().is_some_and(|t| t.neighborhood == *neighborhood) {
+            return;
+        }
+
+        self.table = Some(NeighborTable::build(self.seats.len(), neighborhood, |i| self.neighbors(i, neighborhood)));
+        self.reset_frontier();
+    }
+
+    /// Puts every seat back in the frontier, since the frontier only tracks changes under one rule.
+    fn reset_frontier(&mut self) {
+        self.frontier = (0..self.seats.len() as u32).filter(|i| self.seats[*i as usize] != Seat::Floor).collect();
+        self.in_frontier = self.seats.iter().map(|s| *s != Seat::Floor).collect();
+    }
+
+    fn next_state_for(&self, index: usize, rule: &Rule) -> Seat {
+        let table = self.table.as_ref().unwrap();
+        let adjacent_count = table.neighbors(index)
             .iter()
-            .map(|i| if self.seats[*i] == Seat::Occupied { 1 } else { 0 })
-            .sum();
-
-        match method { 
-            AdjacencyMethod::Proximity => {
-                match adjacent_count {
-                    0 => Seat::Occupied,
-                    1..=3 => self.seats[index],
-                    _ => Seat::Vacant
-                }
-            },
-            AdjacencyMethod::Sight => {
-                match adjacent_count {
-                    0 => Seat::Occupied,
-                    1..=4 => self.seats[index],
-                    _ => Seat::Vacant
-                }
-            }
+            .filter(|i| self.seats[**i as usize] == Seat::Occupied)
+            .count();
+
+        match self.seats[index] {
+            Seat::Vacant if rule.births(adjacent_count) => Seat::Occupied,
+            Seat::Occupied if !rule.survives(adjacent_count) => Seat::Vacant,
+            seat => seat
         }
     }
 
-    fn step(&mut self, method: AdjacencyMethod) -> bool {
-        let mut changed = false;
-        let mut new_seats = Vec::new();
+    /// Only seats that changed last step, or that have a neighbor which did, can change now.
+    fn step(&mut self, rule: &Rule) -> bool {
+        self.prepare(&rule.neighborhood);
+        self.changed.clear();
+
+        for position in 0..self.frontier.len() {
+            let index = self.frontier[position] as usize;
+            let new_state = self.next_state_for(index, rule);
 
-        for index in 0..self.seats.len() {
-            let current_state = self.seats[index];
-            let new_state = self.next_state_for(index, method);
+            if new_state != self.seats[index] {
+                self.next[index] = new_state;
+                self.changed.push(index as u32);
+            }
+        }
 
-            if new_state != current_state { changed = true; }
+        for index in self.frontier.drain(..) {
+            self.in_frontier[index as usize] = false;
+        }
 
-            new_seats.push(new_state);
+        let table = self.table.as_ref().unwrap();
+        for &index in self.changed.iter() {
+            let affected = std::iter::once(&index).chain(table.dependents(index as usize));
+            for &seat in affected {
+                if !self.in_frontier[seat as usize] {
+                    self.in_frontier[seat as usize] = true;
+                    self.frontier.push(seat);
+                }
+            }
         }
 
         // Update the iterations
-        if !changed { return false; }
+        if self.changed.is_empty() { return false; }
         self.iterations += 1;
-        self.seats = new_seats;
-        changed
+        std::mem::swap(&mut self.seats, &mut self.next);
+
+        // `next` now holds the old generation, which differs from the new one only where seats changed
+        for &index in self.changed.iter() {
+            self.next[index as usize] = self.seats[index as usize];
+        }
+
+        true
+    }
+
+    /// The occupied seats packed into bits, used to recognise a generation seen before.
+    fn snapshot(&self) -> Vec<u64> {
+        let mut bits = vec![0; self.seats.len().div_ceil(64)];
+        for (index, seat) in self.seats.iter().enumerate() {
+            if *seat == Seat::Occupied { bits[index / 64] |= 1 << (index % 64); }
+        }
+
+        bits
     }
 
-    fn run_until_stabilized(&mut self, method: AdjacencyMethod) {
-        while self.step(method) {}
-    }   
+    /// Steps until the seats stop changing or repeat an earlier generation, or until
+    /// `max_iterations` is reached. `on_generation` sees the starting state and every change.
+    fn run<F: FnMut(&World)>(&mut self, rule: &Rule, max_iterations: Option<usize>, mut on_generation: F) -> Outcome {
+        self.prepare(&rule.neighborhood);
+        self.reset_frontier();
+
+        let mut seen = HashMap::new();
+        seen.insert(self.snapshot(), self.iterations);
+        on_generation(self);
+
+        loop {
+            if max_iterations.is_some_and(|max| self.iterations >= max) {
+                return Outcome::Unfinished { iterations: self.iterations };
+            }
+
+            if !self.step(rule) {
+                return Outcome::Stable { after: self.iterations };
+            }
+
+            on_generation(self);
+            match seen.entry(self.snapshot()) {
+                Entry::Occupied(first) => return Outcome::Cycle { start: *first.get(), period: self.iterations - first.get() },
+                Entry::Vacant(slot) => { slot.insert(self.iterations); }
+            }
+        }
+    }
+
+    fn run_until_stabilized(&mut self, rule: &Rule) -> Outcome {
+        self.run(rule, None, |_| ())
+    }
+
+    fn occupied(&self) -> usize {
+        self.seats.iter().filter(|s| **s == Seat::Occupied).count()
+    }
+}
+
+enum Visualization {
+    Terminal(Duration),
+    Frames(String)
+}
+
+/// Runs the world, showing every generation along the way.
+fn visualize(world: &mut World, rule: &Rule, max_iterations: Option<usize>, visualization: &Visualization) -> Outcome {
+    world.run(rule, max_iterations, |world| match visualization {
+        Visualization::Terminal(delay) => {
+            print!("{}", render::ansi_frame(world, world.iterations));
+            std::thread::sleep(*delay);
+        },
+        Visualization::Frames(directory) => {
+            let path = Path::new(directory).join(format!("frame-{:04}.ppm", world.iterations));
+            render::image_frame(world, world.iterations, 8).write_ppm(path).unwrap();
+        }
+    })
 }
 
+const USAGE: &str = "usage: day-11 [--max <iterations>] [--animate <delay ms>] [--frames <directory>] [rule...]";
+
 fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut visualization = None;
+    let mut max_iterations = None;
+    let mut rules = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max" => match args.next().and_then(|max| max.parse().ok()) {
+                Some(max) => max_iterations = Some(max),
+                None => return eprintln!("--max needs a number of iterations\n{}", USAGE)
+            },
+            "--animate" => match args.next().and_then(|ms| ms.parse().ok()) {
+                Some(delay) => visualization = Some(Visualization::Terminal(Duration::from_millis(delay))),
+                None => return eprintln!("--animate needs a delay in milliseconds\n{}", USAGE)
+            },
+            "--frames" => match args.next() {
+                Some(directory) => {
+                    std::fs::create_dir_all(&directory).unwrap();
+                    visualization = Some(Visualization::Frames(directory));
+                },
+                None => return eprintln!("--frames needs a directory\n{}", USAGE)
+            },
+            _ => match arg.parse::<Rule>() {
+                Ok(rule) => rules.push(rule),
+                Err(e) => return eprintln!("invalid rule {}: {:?}\n{}", arg, e, USAGE)
+            }
+        }
+    }
+
+    if (visualization.is_some() || max_iterations.is_some()) && rules.is_empty() {
+        rules.push(Rule::proximity());
+    }
+
+    if !rules.is_empty() {
+        for rule in rules.iter() {
+            let mut world = World::new("input.txt");
+
+            let outcome = match &visualization {
+                Some(visualization) => visualize(&mut world, rule, max_iterations, visualization),
+                None => world.run(rule, max_iterations, |_| ())
+            };
+
+            println!("{}: {} with {} occupied seats", rule, outcome, world.occupied());
+        }
+
+        return;
+    }
+
     // Part One
     let mut world = World::new("input.txt");
-    world.run_until_stabilized(AdjacencyMethod::Proximity);
+    world.run_until_stabilized(&Rule::proximity());
 
     println!(
         "Part one: it took {} iterations to stabilize with {} occupied seats", 
-        world.iterations, 
-        world.seats.iter().filter(|s| **s == Seat::Occupied).count()
+        world.iterations,
+        world.occupied()
     );
 
     // Part two
     let mut world = World::new("input.txt");
-    world.run_until_stabilized(AdjacencyMethod::Sight);
+    world.run_until_stabilized(&Rule::sight());
 
     println!(
         "Part two: it took {} iterations to stabilize with {} occupied seats", 
-        world.iterations, 
-        world.seats.iter().filter(|s| **s == Seat::Occupied).count()
+        world.iterations,
+        world.occupied()
     );
 }
 
 #[test]
 fn test_part_one() {
     let mut world = World::new("example.txt");
-    world.run_until_stabilized(AdjacencyMethod::Proximity);
+    world.run_until_stabilized(&Rule::proximity());
 
     assert_eq!(world.iterations, 5);
-    assert_eq!(world.seats.iter().filter(|s| **s == Seat::Occupied).count(), 37);
+    assert_eq!(world.occupied(), 37);
 }
 
 #[test]
 fn test_part_two() {
     let mut world = World::new("example.txt");
-    world.run_until_stabilized(AdjacencyMethod::Sight);
+    world.run_until_stabilized(&Rule::sight());
 
     assert_eq!(world.iterations, 6);
-    assert_eq!(world.seats.iter().filter(|s| **s == Seat::Occupied).count(), 26);
-}
\ No newline at end of file
+    assert_eq!(world.occupied(), 26);
+}
+
+#[test]
+fn test_oscillation() {
+    let mut world = World::new("example.txt");
+    let outcome = world.run_until_stabilized(&"proximity:B0/S".parse().unwrap());
+
+    assert_eq!(outcome, Outcome::Cycle { start: 0, period: 2 });
+    assert_eq!(world.iterations, 2);
+}
+
+#[test]
+fn test_max_iterations() {
+    let mut world = World::new("example.txt");
+    let mut generations = Vec::new();
+    let outcome = world.run(&Rule::proximity(), Some(3), |w| generations.push(w.occupied()));
+
+    assert_eq!(outcome, Outcome::Unfinished { iterations: 3 });
+    assert_eq!(generations, vec![0, 71, 20, 51]);
+
+    let mut world = World::new("example.txt");
+    assert_eq!(world.run_until_stabilized(&Rule::proximity()), Outcome::Stable { after: 5 });
+}
+
+#[test]
+fn test_second_rule_same_neighborhood() {
+    let mut world = World::new("example.txt");
+    assert_eq!(world.run_until_stabilized(&Rule::proximity()), Outcome::Stable { after: 5 });
+
+    // A stable world has an empty frontier, which must not carry over to the next rule
+    let outcome = world.run_until_stabilized(&"proximity:B0/S0-1".parse().unwrap());
+    assert_eq!(outcome, Outcome::Cycle { start: 6, period: 2 });
+    assert_eq!(world.occupied(), 27);
+}
@@ -0,0 +1,72 @@
+use super::rules::Neighborhood;
+
+/// Every seat's neighbors, computed once since floor never changes. Each seat gets a fixed block of
+/// `width` slots (eight for the standard neighborhoods) so lookups never allocate.
+pub struct NeighborTable {
+    pub neighborhood: Neighborhood,
+    width: usize,
+    counts: Vec<usize>,
+    indices: Vec<u32>,
+    dependent_starts: Vec<usize>,
+    dependents: Vec<u32>
+}
+
+impl NeighborTable {
+    /// `neighbors_of` lists the seats (never floor) whose occupancy counts towards a seat.
+    pub fn build<F>(seat_count: usize, neighborhood: &Neighborhood, neighbors_of: F) -> Self
+        where F: Fn(usize) -> Vec<usize>
+    {
+        let lists: Vec<Vec<usize>> = (0..seat_count).map(neighbors_of).collect();
+        let width = lists.iter().map(|l| l.len()).max().unwrap_or(0).max(8);
+
+        let mut counts = Vec::with_capacity(seat_count);
+        let mut indices = vec![0; seat_count * width];
+        let mut dependent_counts = vec![0; seat_count + 1];
+
+        for (seat, list) in lists.iter().enumerate() {
+            counts.push(list.len());
+            for (slot, neighbor) in list.iter().enumerate() {
+                indices[seat * width + slot] = *neighbor as u32;
+                dependent_counts[*neighbor + 1] += 1;
+            }
+        }
+
+        // Seats that have a given seat as a neighbor, laid out back to back
+        let mut dependent_starts = dependent_counts;
+        for i in 1..dependent_starts.len() {
+            dependent_starts[i] += dependent_starts[i - 1];
+        }
+
+        let mut fill = dependent_starts.clone();
+        let mut dependents = vec![0; dependent_starts[seat_count]];
+        for (seat, list) in lists.iter().enumerate() {
+            for neighbor in list {
+                dependents[fill[*neighbor]] = seat as u32;
+                fill[*neighbor] += 1;
+            }
+        }
+
+        NeighborTable { neighborhood: neighborhood.clone(), width, counts, indices, dependent_starts, dependents }
+    }
+
+    pub fn neighbors(&self, seat: usize) -> &[u32] {
+        let start = seat * self.width;
+        &self.indices[start..start + self.counts[seat]]
+    }
+
+    /// The seats whose next state depends on this one.
+    pub fn dependents(&self, seat: usize) -> &[u32] {
+        &self.dependents[self.dependent_starts[seat]..self.dependent_starts[seat + 1]]
+    }
+}
+
+#[test]
+fn test_table_layout() {
+    // A 1x4 strip where each seat's neighbor is the one to its right
+    let table = NeighborTable::build(4, &Neighborhood::Offsets(vec![(1, 0)]), |seat| if seat < 3 { vec![seat + 1] } else { vec![] });
+
+    assert_eq!(table.neighbors(0), &[1]);
+    assert_eq!(table.neighbors(3), &[] as &[u32]);
+    assert_eq!(table.dependents(0), &[] as &[u32]);
+    assert_eq!(table.dependents(2), &[1]);
+}
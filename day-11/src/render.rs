@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use super::{Seat, World};
+
+const OCCUPIED_COLOR: [u8; 3] = [220, 60, 50];
+const VACANT_COLOR: [u8; 3] = [60, 170, 90];
+const FLOOR_COLOR: [u8; 3] = [30, 30, 30];
+const TEXT_COLOR: [u8; 3] = [255, 255, 255];
+
+/// 3x5 bitmaps for the digits, one row per entry with the high bit on the left.
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111]
+];
+
+/// A frame for the terminal: clears the screen, then prints a header and the seats in color.
+pub fn ansi_frame(world: &World, generation: usize) -> String {
+    let mut frame = format!("\x1b[H\x1b[2JGeneration {} ({} occupied)\n", generation, world.occupied());
+
+    for row in world.seats.chunks(world.length) {
+        for seat in row {
+            let color = match seat {
+                Seat::Occupied => "\x1b[31m",
+                Seat::Vacant => "\x1b[32m",
+                Seat::Floor => "\x1b[90m"
+            };
+
+            frame.push_str(&format!("{}{}", color, seat));
+        }
+
+        frame.push_str("\x1b[0m\n");
+    }
+
+    frame
+}
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>
+}
+
+impl Image {
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 3]) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                self.pixels[py * self.width + px] = color;
+            }
+        }
+    }
+
+    /// Draws a number in the top left corner on a dark backing so it stays readable.
+    fn overlay_number(&mut self, number: usize, scale: usize) {
+        let text = number.to_string();
+        let backing = [0, 0, 0];
+        self.fill(0, 0, (text.len() * 4 + 1) * scale, 7 * scale, backing);
+
+        for (position, digit) in text.bytes().map(|b| (b - b'0') as usize).enumerate() {
+            for (row, bits) in DIGITS[digit].iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) != 0 {
+                        self.fill((1 + position * 4 + column) * scale, (1 + row) * scale, scale, scale, TEXT_COLOR);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn write_ppm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+
+        for pixel in &self.pixels {
+            writer.write_all(pixel)?;
+        }
+
+        writer.flush()
+    }
+}
+
+/// Renders each seat as a `scale` pixel square with the generation number in the corner.
+pub fn image_frame(world: &World, generation: usize, scale: usize) -> Image {
+    let mut image = Image {
+        width: world.length * scale,
+        height: world.height * scale,
+        pixels: vec![FLOOR_COLOR; world.length * world.height * scale * scale]
+    };
+
+    for (index, seat) in world.seats.iter().enumerate() {
+        let color = match seat {
+            Seat::Occupied => OCCUPIED_COLOR,
+            Seat::Vacant => VACANT_COLOR,
+            Seat::Floor => FLOOR_COLOR
+        };
+
+        image.fill((index % world.length) * scale, (index / world.length) * scale, scale, scale, color);
+    }
+
+    image.overlay_number(generation, (scale / 2).max(1));
+    image
+}
+
+#[test]
+fn test_ansi_frame() {
+    let world = World::new("example.txt");
+    let frame = ansi_frame(&world, 3);
+
+    assert!(frame.starts_with("\x1b[H\x1b[2JGeneration 3 (0 occupied)\n"));
+    assert_eq!(frame.lines().count(), 11);
+}
+
+#[test]
+fn test_image_frame() {
+    let world = World::new("example.txt");
+    let image = image_frame(&world, 7, 4);
+
+    assert_eq!((image.width, image.height), (40, 40));
+    assert_eq!(image.pixels[39 * 40 + 39], VACANT_COLOR);
+    assert_eq!(image.pixels[39 * 40 + 29], FLOOR_COLOR);
+
+    // The seven's top bar starts one (scaled) pixel in from the corner
+    assert_eq!(image.pixels[0], [0, 0, 0]);
+    assert_eq!(image.pixels[2 * 40 + 2], TEXT_COLOR);
+}
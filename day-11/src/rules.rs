@@ -0,0 +1,205 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Neighborhood {
+    /// The eight touching seats.
+    Proximity,
+    /// The first seat visible in each of the eight directions, looking past floor.
+    Sight,
+    /// The eight seats a chess knight's move away.
+    Knight,
+    /// Every seat within the given Chebyshev distance.
+    Radius(usize),
+    /// Any fixed set of (x, y) offsets.
+    Offsets(Vec<(i32, i32)>)
+}
+
+pub const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1), (0, -1), (1, -1),
+    (-1,  0),          (1,  0),
+    (-1,  1), (0,  1), (1,  1)
+];
+
+const KNIGHT_MOVES: [(i32, i32); 8] = [
+    (-2, -1), (-1, -2), (1, -2), (2, -1),
+    (-2,  1), (-1,  2), (1,  2), (2,  1)
+];
+
+impl Neighborhood {
+    /// The offsets to check for neighborhoods that do not depend on the floor plan.
+    pub fn offsets(&self) -> Vec<(i32, i32)> {
+        match self {
+            Neighborhood::Proximity | Neighborhood::Sight => DIRECTIONS.to_vec(),
+            Neighborhood::Knight => KNIGHT_MOVES.to_vec(),
+            Neighborhood::Radius(r) => {
+                let r = *r as i32;
+                (-r..=r).flat_map(|y| (-r..=r).map(move |x| (x, y)))
+                    .filter(|offset| *offset != (0, 0))
+                    .collect()
+            },
+            Neighborhood::Offsets(offsets) => offsets.clone()
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum RuleError {
+    MissingSeparator(String),
+    UnknownNeighborhood(String),
+    InvalidCounts(String)
+}
+
+impl FromStr for Neighborhood {
+    type Err = RuleError;
+
+    /// `proximity`, `sight`, `knight`, `radius<r>` or `offsets<x>,<y>;<x>,<y>...`
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let unknown = || RuleError::UnknownNeighborhood(name.to_string());
+
+        match name {
+            "proximity" => Ok(Neighborhood::Proximity),
+            "sight" => Ok(Neighborhood::Sight),
+            "knight" => Ok(Neighborhood::Knight),
+            _ if name.starts_with("radius") => name[6..].parse().map(Neighborhood::Radius).map_err(|_| unknown()),
+            _ if name.starts_with("offsets") => {
+                name[7..].split(';')
+                    .map(|pair| {
+                        let mut parts = pair.split(',').map(|p| p.trim().parse::<i32>());
+                        match (parts.next(), parts.next(), parts.next()) {
+                            (Some(Ok(x)), Some(Ok(y)), None) => Ok((x, y)),
+                            _ => Err(unknown())
+                        }
+                    })
+                    .collect::<Result<Vec<(i32, i32)>, RuleError>>()
+                    .map(Neighborhood::Offsets)
+            },
+            _ => Err(unknown())
+        }
+    }
+}
+
+/// A birth/survival rule: a vacant seat fills when its occupied neighbor count is in `birth`, and
+/// an occupied seat stays filled while the count is in `survival`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub neighborhood: Neighborhood,
+    pub birth: Vec<RangeInclusive<usize>>,
+    pub survival: Vec<RangeInclusive<usize>>
+}
+
+impl Rule {
+    pub fn proximity() -> Self {
+        Rule { neighborhood: Neighborhood::Proximity, birth: vec![0..=0], survival: vec![0..=3] }
+    }
+
+    pub fn sight() -> Self {
+        Rule { neighborhood: Neighborhood::Sight, birth: vec![0..=0], survival: vec![0..=4] }
+    }
+
+    pub fn births(&self, count: usize) -> bool {
+        self.birth.iter().any(|range| range.contains(&count))
+    }
+
+    pub fn survives(&self, count: usize) -> bool {
+        self.survival.iter().any(|range| range.contains(&count))
+    }
+}
+
+impl fmt::Display for Neighborhood {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Neighborhood::Proximity => write!(f, "proximity"),
+            Neighborhood::Sight => write!(f, "sight"),
+            Neighborhood::Knight => write!(f, "knight"),
+            Neighborhood::Radius(r) => write!(f, "radius{}", r),
+            Neighborhood::Offsets(offsets) => {
+                let pairs: Vec<String> = offsets.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+                write!(f, "offsets{}", pairs.join(";"))
+            }
+        }
+    }
+}
+
+fn format_counts(counts: &[RangeInclusive<usize>]) -> String {
+    let parts: Vec<String> = counts.iter()
+        .map(|range| if range.start() == range.end() { range.start().to_string() } else { format!("{}-{}", range.start(), range.end()) })
+        .collect();
+
+    parts.join(",")
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:B{}/S{}", self.neighborhood, format_counts(&self.birth), format_counts(&self.survival))
+    }
+}
+
+/// Counts like `0`, `0-3` or `0,2-4,6`; an empty list matches nothing.
+fn parse_counts(counts: &str) -> Result<Vec<RangeInclusive<usize>>, RuleError> {
+    let invalid = || RuleError::InvalidCounts(counts.to_string());
+
+    counts.split(',')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut bounds = part.splitn(2, '-').map(|b| b.parse::<usize>());
+            match (bounds.next(), bounds.next()) {
+                (Some(Ok(low)), None) => Ok(low..=low),
+                (Some(Ok(low)), Some(Ok(high))) if low <= high => Ok(low..=high),
+                _ => Err(invalid())
+            }
+        })
+        .collect()
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    /// `<neighborhood>:B<counts>/S<counts>`, for example `sight:B0/S0-4`.
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let missing = || RuleError::MissingSeparator(rule.to_string());
+
+        let (neighborhood, thresholds) = rule.split_once(':').ok_or_else(missing)?;
+        let (birth, survival) = thresholds.split_once('/').ok_or_else(missing)?;
+
+        let birth = birth.strip_prefix('B').ok_or_else(|| RuleError::InvalidCounts(birth.to_string()))?;
+        let survival = survival.strip_prefix('S').ok_or_else(|| RuleError::InvalidCounts(survival.to_string()))?;
+
+        Ok(Rule { neighborhood: neighborhood.parse()?, birth: parse_counts(birth)?, survival: parse_counts(survival)? })
+    }
+}
+
+#[test]
+fn test_parse_rules() {
+    assert_eq!("proximity:B0/S0-3".parse(), Ok(Rule::proximity()));
+    assert_eq!("sight:B0/S0-4".parse(), Ok(Rule::sight()));
+
+    let rule: Rule = "radius2:B0,3/S1-2,5".parse().unwrap();
+    assert_eq!(rule.neighborhood, Neighborhood::Radius(2));
+    assert_eq!(rule.birth, vec![0..=0, 3..=3]);
+    assert_eq!(rule.survival, vec![1..=2, 5..=5]);
+
+    let rule: Rule = "offsets0,-1;0,1:B/S0".parse().unwrap();
+    assert_eq!(rule.neighborhood, Neighborhood::Offsets(vec![(0, -1), (0, 1)]));
+    assert!(!rule.births(0));
+
+    for text in &["radius2:B0,3/S1-2,5", "offsets0,-1;0,1:B/S0", "knight:B0/S0-2"] {
+        assert_eq!(text.parse::<Rule>().unwrap().to_string(), *text);
+    }
+}
+
+#[test]
+fn test_rule_errors() {
+    assert_eq!("proximity".parse::<Rule>(), Err(RuleError::MissingSeparator("proximity".to_string())));
+    assert_eq!("hex:B0/S0".parse::<Rule>(), Err(RuleError::UnknownNeighborhood("hex".to_string())));
+    assert_eq!("knight:B3-1/S0".parse::<Rule>(), Err(RuleError::InvalidCounts("3-1".to_string())));
+    assert_eq!("knight:0/S0".parse::<Rule>(), Err(RuleError::InvalidCounts("0".to_string())));
+}
+
+#[test]
+fn test_neighborhood_offsets() {
+    assert_eq!(Neighborhood::Radius(1).offsets(), DIRECTIONS.to_vec());
+    assert_eq!(Neighborhood::Radius(2).offsets().len(), 24);
+    assert_eq!(Neighborhood::Knight.offsets().len(), 8);
+}
@@ -1,3 +1,11 @@
+mod track;
+
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, SubAssign};
+
+use track::Track;
+
+#[derive(Debug, PartialEq)]
 enum Instruction {
     Forward(u32),
     Left(u32),
@@ -8,157 +16,354 @@ enum Instruction {
     West(u32)
 }
 
-impl From<&str> for Instruction {
-    fn from(st: &str) -> Self {
-        let amount = st[1..].parse().unwrap();
+#[derive(Debug, PartialEq)]
+enum NavigationError {
+    Empty,
+    UnknownAction(char),
+    InvalidAmount(String),
+    UnsupportedRotation(u32)
+}
+
+impl Instruction {
+    fn parse(st: &str) -> Result<Self, NavigationError> {
+        let action = st.chars().next().ok_or(NavigationError::Empty)?;
+        let amount = st[action.len_utf8()..].parse()
+            .map_err(|_| NavigationError::InvalidAmount(st.to_string()))?;
 
-        match st.chars().nth(0).unwrap() {
-            'N' => Instruction::North(amount),
-            'S' => Instruction::South(amount),
-            'E' => Instruction::East(amount),
-            'W' => Instruction::West(amount),
-            'L' => Instruction::Left(amount),
-            'R' => Instruction::Right(amount),
-            'F' => Instruction::Forward(amount),
-            _ => panic!()
+        match action {
+            'N' => Ok(Instruction::North(amount)),
+            'S' => Ok(Instruction::South(amount)),
+            'E' => Ok(Instruction::East(amount)),
+            'W' => Ok(Instruction::West(amount)),
+            'L' => Ok(Instruction::Left(amount)),
+            'R' => Ok(Instruction::Right(amount)),
+            'F' => Ok(Instruction::Forward(amount)),
+            other => Err(NavigationError::UnknownAction(other))
         }
     }
 }
 
-struct Ship {
-    heading: u16,
-    x: i32,
-    y: i32
+fn read_instructions(input: &str) -> Result<Vec<Instruction>, NavigationError> {
+    std::fs::read_to_string(input).unwrap()
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(Instruction::parse)
+        .collect()
 }
 
-struct Waypoint {
-    x: i32,
-    y: i32
+/// A number to navigate with. Integer coordinates only allow quarter turns, as in the puzzle, and
+/// keep every position exact; floating point coordinates may turn by any angle.
+trait Coordinate: Copy + PartialEq + fmt::Display + Add<Output = Self> + Mul<Output = Self> + AddAssign + SubAssign {
+    fn from_amount(amount: u32) -> Self;
+    fn abs(self) -> Self;
+    fn to_f64(self) -> f64;
+
+    fn validate(degrees: u32) -> Result<(), NavigationError>;
+
+    /// Rotates a vector counter-clockwise by an angle that has passed `validate`.
+    fn rotate(x: Self, y: Self, degrees: i64) -> (Self, Self);
 }
 
-impl Waypoint {
-    fn new() -> Self {
-        Waypoint { x: 10, y: 1 }
+impl Coordinate for i64 {
+    fn from_amount(amount: u32) -> Self {
+        amount as i64
+    }
+
+    fn abs(self) -> Self {
+        i64::abs(self)
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn validate(degrees: u32) -> Result<(), NavigationError> {
+        if degrees.is_multiple_of(90) { Ok(()) } else { Err(NavigationError::UnsupportedRotation(degrees)) }
+    }
+
+    fn rotate(x: i64, y: i64, degrees: i64) -> (i64, i64) {
+        match degrees.rem_euclid(360) {
+            0 => (x, y),
+            90 => (-y, x),
+            180 => (-x, -y),
+            270 => (y, -x),
+            other => unreachable!("{} degrees is not a quarter turn", other)
+        }
+    }
+}
+
+impl Coordinate for f64 {
+    fn from_amount(amount: u32) -> Self {
+        amount as f64
     }
 
-    fn rotate_ccw(&mut self, amount: u32) {
-        let mut amount_left = amount;
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
 
-        while amount_left > 0 {
-            let (x, y) = (self.x, self.y);
+    fn to_f64(self) -> f64 {
+        self
+    }
 
-            self.x = -y;
-            self.y = x;
+    fn validate(_degrees: u32) -> Result<(), NavigationError> {
+        Ok(())
+    }
 
-            amount_left -= 90;
+    /// Quarter turns swap components so that whole-number positions stay whole.
+    fn rotate(x: f64, y: f64, degrees: i64) -> (f64, f64) {
+        match degrees.rem_euclid(360) {
+            0 => (x, y),
+            90 => (-y, x),
+            180 => (-x, -y),
+            270 => (y, -x),
+            other => {
+                let (sin, cos) = (other as f64).to_radians().sin_cos();
+                (x * cos - y * sin, x * sin + y * cos)
+            }
         }
     }
+}
 
-    fn rotate_cw(&mut self, amount: u32) {
-        let mut amount_left = amount;
+trait Navigator {
+    type Coordinate: Coordinate;
 
-        while amount_left > 0 {
-            let (x, y) = (self.x, self.y);
+    fn apply(&mut self, instruction: &Instruction) -> Result<(), NavigationError>;
+    fn position(&self) -> (Self::Coordinate, Self::Coordinate);
 
-            self.x = y;
-            self.y = -x;
+    /// The direction of travel in degrees counter-clockwise from east.
+    fn heading(&self) -> f64;
 
-            amount_left -= 90;
+    fn execute(&mut self, input: &str) -> Result<(), NavigationError> {
+        for instruction in read_instructions(input)? {
+            self.apply(&instruction)?;
         }
+
+        Ok(())
+    }
+
+    fn manhattan_distance(&self) -> Self::Coordinate {
+        let (x, y) = self.position();
+        x.abs() + y.abs()
+    }
+}
+
+struct Ship<C: Coordinate> {
+    heading: i64,
+    x: C,
+    y: C
+}
+
+impl<C: Coordinate> Ship<C> {
+    fn new() -> Self {
+        Ship { heading: 0, x: C::from_amount(0), y: C::from_amount(0) }
     }
+}
+
+impl<C: Coordinate> Navigator for Ship<C> {
+    type Coordinate = C;
 
-    fn apply(&mut self, ship: &mut Ship, instruction: &Instruction) {
+    fn apply(&mut self, instruction: &Instruction) -> Result<(), NavigationError> {
         match instruction {
             Instruction::Forward(amount) => {
-                ship.x += self.x * (*amount as i32);
-                ship.y += self.y * (*amount as i32);
+                let (dx, dy) = C::rotate(C::from_amount(*amount), C::from_amount(0), self.heading);
+                self.x += dx;
+                self.y += dy;
             },
-            Instruction::Left(amount) => self.rotate_ccw(*amount),
-            Instruction::Right(amount) => self.rotate_cw(*amount),
-            Instruction::North(amount) => self.y += *amount as i32,
-            Instruction::South(amount) => self.y -= *amount as i32,
-            Instruction::East(amount) => self.x += *amount as i32,
-            Instruction::West(amount) => self.x -= *amount as i32
+            Instruction::Left(amount) => {
+                C::validate(*amount)?;
+                self.heading = (self.heading + *amount as i64).rem_euclid(360);
+            },
+            Instruction::Right(amount) => {
+                C::validate(*amount)?;
+                self.heading = (self.heading - *amount as i64).rem_euclid(360);
+            },
+            Instruction::North(amount) => self.y += C::from_amount(*amount),
+            Instruction::South(amount) => self.y -= C::from_amount(*amount),
+            Instruction::East(amount) => self.x += C::from_amount(*amount),
+            Instruction::West(amount) => self.x -= C::from_amount(*amount)
         }
+
+        Ok(())
+    }
+
+    fn position(&self) -> (C, C) {
+        (self.x, self.y)
     }
 
-    fn execute(&mut self, ship: &mut Ship, input: &str) {
-        std::fs::read_to_string(input).unwrap()
-            .lines()
-            .map(|line| Instruction::from(line))
-            .for_each(|instr| self.apply(ship, &instr));
+    fn heading(&self) -> f64 {
+        self.heading as f64
     }
 }
 
-impl Ship {
+/// Navigation by moving a waypoint relative to the ship, which it carries along.
+struct Waypoint<C: Coordinate> {
+    ship: Ship<C>,
+    x: C,
+    y: C
+}
+
+impl<C: Coordinate> Waypoint<C> {
     fn new() -> Self {
-        Ship { heading: 0, x: 0, y: 0 }
+        Waypoint { ship: Ship::new(), x: C::from_amount(10), y: C::from_amount(1) }
+    }
+
+    fn rotate(&mut self, degrees: u32, counter_clockwise: bool) -> Result<(), NavigationError> {
+        C::validate(degrees)?;
+
+        let signed = if counter_clockwise { degrees as i64 } else { -(degrees as i64) };
+        let (x, y) = C::rotate(self.x, self.y, signed);
+        self.x = x;
+        self.y = y;
+
+        Ok(())
     }
+}
+
+impl<C: Coordinate> Navigator for Waypoint<C> {
+    type Coordinate = C;
 
-    fn apply(&mut self, instruction: &Instruction) {
+    fn apply(&mut self, instruction: &Instruction) -> Result<(), NavigationError> {
         match instruction {
-            Instruction::Forward(amount) => match self.heading {
-                0 => self.apply(&Instruction::East(*amount)),
-                90 => self.apply(&Instruction::North(*amount)),
-                180 => self.apply(&Instruction::West(*amount)),
-                270 => self.apply(&Instruction::South(*amount)),
-                _ => panic!()
+            Instruction::Forward(amount) => {
+                self.ship.x += self.x * C::from_amount(*amount);
+                self.ship.y += self.y * C::from_amount(*amount);
             },
-            Instruction::Left(amount) => self.heading = turn_degrees(self.heading, *amount as i32),
-            Instruction::Right(amount) => self.heading = turn_degrees(self.heading, -(*amount as i32)),
-            Instruction::North(amount) => self.y += *amount as i32,
-            Instruction::South(amount) => self.y -= *amount as i32,
-            Instruction::East(amount) => self.x += *amount as i32,
-            Instruction::West(amount) => self.x -= *amount as i32
+            Instruction::Left(amount) => self.rotate(*amount, true)?,
+            Instruction::Right(amount) => self.rotate(*amount, false)?,
+            Instruction::North(amount) => self.y += C::from_amount(*amount),
+            Instruction::South(amount) => self.y -= C::from_amount(*amount),
+            Instruction::East(amount) => self.x += C::from_amount(*amount),
+            Instruction::West(amount) => self.x -= C::from_amount(*amount)
         }
+
+        Ok(())
     }
 
-    fn execute(&mut self, input: &str) {
-        std::fs::read_to_string(input).unwrap()
-            .lines()
-            .map(|line| Instruction::from(line))
-            .for_each(|instr| self.apply(&instr));
+    fn position(&self) -> (C, C) {
+        self.ship.position()
     }
-}
 
-fn turn_degrees(heading: u16, turn: i32) -> u16 {
-    let mut raw = (heading as i32 + turn) % 360;
-    while raw < 0 { raw += 360; }
-    raw as u16
+    fn heading(&self) -> f64 {
+        self.y.to_f64().atan2(self.x.to_f64()).to_degrees().rem_euclid(360.0)
+    }
 }
 
-fn manhattan_distance(x1: i32, y1: i32, x2: i32, y2: i32) -> i32 {
-    (x1 - x2).abs() + (y1 - y2).abs()
+/// Records the voyage, printing its summary and writing `<name>.csv` and `<name>.svg` to `directory`.
+fn export_track<N: Navigator>(navigator: &mut N, input: &str, directory: &str, name: &str) -> Result<(), NavigationError> {
+    let track = Track::record(navigator, &read_instructions(input)?)?;
+    let summary = track.summary();
+
+    println!(
+        "{}: max distance {}, travelled {:.1}, bounding box ({}, {}) to ({}, {})",
+        name, summary.max_distance, summary.travelled, summary.min.0, summary.min.1, summary.max.0, summary.max.1
+    );
+
+    let base = std::path::Path::new(directory).join(name);
+    std::fs::write(base.with_extension("csv"), track.to_csv()).unwrap();
+    std::fs::write(base.with_extension("svg"), track.to_svg()).unwrap();
+    Ok(())
 }
 
-fn main() {
+/// Both parts, with positions kept in `C`.
+fn solve<C: Coordinate>(input: &str, track_directory: Option<&str>) {
+    if let Some(directory) = track_directory {
+        std::fs::create_dir_all(directory).unwrap();
+        export_track(&mut Ship::<C>::new(), input, directory, "ship").unwrap();
+        export_track(&mut Waypoint::<C>::new(), input, directory, "waypoint").unwrap();
+    }
+
     // Part 1
-    let mut ship = Ship::new();
-    ship.execute("input.txt");
-    println!("Part one: {}", manhattan_distance(ship.x, ship.y, 0, 0));
+    let mut ship = Ship::<C>::new();
+    match ship.execute(input) {
+        Ok(()) => println!("Part one: {}", ship.manhattan_distance()),
+        Err(e) => println!("Part one failed: {:?}", e)
+    }
 
     // Part 2
-    let mut ship = Ship::new();
-    let mut waypoint = Waypoint::new();
+    let mut waypoint = Waypoint::<C>::new();
+    match waypoint.execute(input) {
+        Ok(()) => println!("Part two: {}", waypoint.manhattan_distance()),
+        Err(e) => println!("Part two failed: {:?}", e)
+    }
+}
 
-    waypoint.execute(&mut ship, "input.txt");
+const USAGE: &str = "usage: day-12 [--any-angle] [--track <directory>] [input]";
 
-    println!("Part two: {}", manhattan_distance(ship.x, ship.y, 0, 0));
+/// `--any-angle` allows turns that are not quarter turns, at the cost of floating point positions.
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut any_angle = false;
+    let mut track_directory = None;
+    let mut input = String::from("input.txt");
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--any-angle" => any_angle = true,
+            "--track" => match args.next() {
+                Some(directory) => track_directory = Some(directory),
+                None => return eprintln!("--track needs a directory\n{}", USAGE)
+            },
+            option if option.starts_with("--") => return eprintln!("unknown option {}\n{}", option, USAGE),
+            _ => input = arg
+        }
+    }
+
+    if any_angle {
+        solve::<f64>(&input, track_directory.as_deref());
+    } else {
+        solve::<i64>(&input, track_directory.as_deref());
+    }
 }
 
 #[test]
 fn test_part_1() {
-    let mut ship = Ship::new();
-    ship.execute("example.txt");
+    let mut ship = Ship::<i64>::new();
+    ship.execute("example.txt").unwrap();
 
-    assert_eq!(manhattan_distance(ship.x, ship.y, 0, 0), 25);
+    assert_eq!(ship.manhattan_distance(), 25);
 }
 
 #[test]
 fn test_part_2() {
-    let mut ship = Ship::new();
-    let mut waypoint = Waypoint::new();
-    waypoint.execute(&mut ship, "example.txt");
+    let mut waypoint = Waypoint::<i64>::new();
+    waypoint.execute("example.txt").unwrap();
+
+    assert_eq!(waypoint.manhattan_distance(), 286);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(Instruction::parse("F10"), Ok(Instruction::Forward(10)));
+    assert_eq!(Instruction::parse(""), Err(NavigationError::Empty));
+    assert_eq!(Instruction::parse("X10"), Err(NavigationError::UnknownAction('X')));
+    assert_eq!(Instruction::parse("R-90"), Err(NavigationError::InvalidAmount("R-90".to_string())));
+}
+
+#[test]
+fn test_rotation_validation() {
+    let mut ship = Ship::<i64>::new();
+    assert_eq!(ship.apply(&Instruction::Right(45)), Err(NavigationError::UnsupportedRotation(45)));
+    assert_eq!(ship.apply(&Instruction::Left(450)), Ok(()));
+    assert_eq!(ship.heading(), 90.0);
+
+    let mut waypoint = Waypoint::<i64>::new();
+    assert_eq!(waypoint.apply(&Instruction::Left(100)), Err(NavigationError::UnsupportedRotation(100)));
+    assert_eq!((waypoint.x, waypoint.y), (10, 1));
+}
+
+#[test]
+fn test_arbitrary_angles() {
+    let mut ship = Ship::<f64>::new();
+    ship.apply(&Instruction::Left(45)).unwrap();
+    ship.apply(&Instruction::Forward(10)).unwrap();
 
-    assert_eq!(manhattan_distance(ship.x, ship.y, 0, 0), 286);
-}
\ No newline at end of file
+    let (x, y) = ship.position();
+    assert!((x - 50f64.sqrt()).abs() < 1e-9);
+    assert!((y - 50f64.sqrt()).abs() < 1e-9);
+
+    let mut waypoint = Waypoint::<f64>::new();
+    waypoint.apply(&Instruction::Right(30)).unwrap();
+    waypoint.apply(&Instruction::Right(60)).unwrap();
+    assert!((waypoint.x - 1.0).abs() < 1e-9 && (waypoint.y + 10.0).abs() < 1e-9);
+    assert!((waypoint.heading() - 275.71).abs() < 0.01);
+}
@@ -0,0 +1,120 @@
+use super::{Coordinate, Instruction, NavigationError, Navigator};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    pub step: usize,
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    /// The furthest Manhattan distance from the origin at any point of the voyage.
+    pub max_distance: f64,
+    /// The length of the route, moving in straight lines between points.
+    pub travelled: f64,
+    pub min: (f64, f64),
+    pub max: (f64, f64)
+}
+
+/// The ship's position and heading before the voyage and after every instruction.
+pub struct Track {
+    pub points: Vec<TrackPoint>
+}
+
+impl Track {
+    pub fn record<N: Navigator>(navigator: &mut N, instructions: &[Instruction]) -> Result<Track, NavigationError> {
+        let point = |step, navigator: &N| {
+            let (x, y) = navigator.position();
+            TrackPoint { step, x: x.to_f64(), y: y.to_f64(), heading: navigator.heading() }
+        };
+
+        let mut points = vec![point(0, navigator)];
+        for (index, instruction) in instructions.iter().enumerate() {
+            navigator.apply(instruction)?;
+            points.push(point(index + 1, navigator));
+        }
+
+        Ok(Track { points })
+    }
+
+    pub fn summary(&self) -> Summary {
+        let first = self.points[0];
+        let mut summary = Summary { max_distance: 0.0, travelled: 0.0, min: (first.x, first.y), max: (first.x, first.y) };
+
+        for point in &self.points {
+            summary.max_distance = summary.max_distance.max(point.x.abs() + point.y.abs());
+            summary.min = (summary.min.0.min(point.x), summary.min.1.min(point.y));
+            summary.max = (summary.max.0.max(point.x), summary.max.1.max(point.y));
+        }
+
+        summary.travelled = self.points.windows(2)
+            .map(|pair| (pair[1].x - pair[0].x).hypot(pair[1].y - pair[0].y))
+            .sum();
+
+        summary
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("step,x,y,heading\n");
+
+        for point in &self.points {
+            csv.push_str(&format!("{},{},{},{}\n", point.step, point.x, point.y, point.heading));
+        }
+
+        csv
+    }
+
+    /// Plots the route as a polyline with north up, marking the start and end.
+    pub fn to_svg(&self) -> String {
+        let summary = self.summary();
+        let width = (summary.max.0 - summary.min.0).max(1.0);
+        let height = (summary.max.1 - summary.min.1).max(1.0);
+        let margin = width.max(height) * 0.05;
+
+        let points: Vec<String> = self.points.iter().map(|p| format!("{},{}", p.x, 0.0 - p.y)).collect();
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        let radius = margin / 2.0;
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+            summary.min.0 - margin, -summary.max.1 - margin, width + 2.0 * margin, height + 2.0 * margin
+        );
+        svg.push_str(&format!(
+            "  <polyline fill=\"none\" stroke=\"steelblue\" stroke-width=\"2\" vector-effect=\"non-scaling-stroke\" points=\"{}\"/>\n",
+            points.join(" ")
+        ));
+        svg.push_str(&format!("  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"green\"/>\n", first.x, 0.0 - first.y, radius));
+        svg.push_str(&format!("  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"red\"/>\n", last.x, 0.0 - last.y, radius));
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+}
+
+#[test]
+fn test_ship_track() {
+    let instructions = super::read_instructions("example.txt").unwrap();
+    let track = Track::record(&mut super::Ship::<i64>::new(), &instructions).unwrap();
+
+    assert_eq!(track.points.len(), 6);
+    assert_eq!(track.points[4], TrackPoint { step: 4, x: 17.0, y: 3.0, heading: 270.0 });
+    assert_eq!(track.summary(), Summary { max_distance: 25.0, travelled: 31.0, min: (0.0, -8.0), max: (17.0, 3.0) });
+}
+
+#[test]
+fn test_exports() {
+    let instructions = super::read_instructions("example.txt").unwrap();
+    let track = Track::record(&mut super::Waypoint::<i64>::new(), &instructions).unwrap();
+
+    let csv = track.to_csv();
+    assert_eq!(csv.lines().next(), Some("step,x,y,heading"));
+    assert_eq!(csv.lines().nth(1).unwrap().split(',').take(3).collect::<Vec<&str>>(), vec!["0", "0", "0"]);
+    assert_eq!(csv.lines().last().unwrap().split(',').take(3).collect::<Vec<&str>>(), vec!["5", "214", "-72"]);
+
+    let svg = track.to_svg();
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("points=\"0,0 100,-10 100,-10 170,-38 170,-38 214,72\""));
+}
@@ -1,43 +1,97 @@
-#[derive(Debug, Copy, Clone)]
-struct PeriodicWithOffsets {
+mod timetable;
+
+use std::time::Instant;
+
+use timetable::Timetable;
+
+/// A bus that must depart `offset` minutes after the sought timestamp.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Bus {
     period: u64,
-    offset_from_sync: u64,
-    first_sync: u64
+    offset: u64
 }
 
-impl PeriodicWithOffsets {
-    fn find_first_sync(&self, other: &PeriodicWithOffsets) -> u64 {
-        let mut x1 = self.first_sync;
-        let mut progress = 0;
+/// The set of timestamps `t` with `t ≡ remainder (mod modulus)`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Congruence {
+    remainder: u128,
+    modulus: u128
+}
 
-        while x1 < self.offset_from_sync { x1 += self.period; }
+#[derive(Debug, PartialEq)]
+enum CrtError {
+    /// The two congruences can never hold at the same time.
+    NoSolution(Congruence, Congruence),
+    Overflow
+}
 
-        loop {
-            x1 += self.period;
-            progress += 1;
+impl Bus {
+    fn congruence(&self) -> Congruence {
+        let modulus = self.period as u128;
+        Congruence { remainder: (modulus - self.offset as u128 % modulus) % modulus, modulus }
+    }
+}
 
-            if progress == 100_000 {
-                print!(".");
-                progress = 0;
-            }
+/// Returns `(g, x, y)` with `a * x + b * y = g = gcd(a, b)`.
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
 
-            let relative_x1 = x1 + other.offset_from_sync - self.offset_from_sync;
+/// `a * b % modulus` without overflowing, falling back to doubling when the product is too big.
+fn mul_mod(a: u128, b: u128, modulus: u128) -> u128 {
+    if let Some(product) = a.checked_mul(b) {
+        return product % modulus;
+    }
 
-            if (relative_x1 >= other.first_sync) && (relative_x1 - other.first_sync) % other.period == 0 {
-                println!("{} {} {} {} {}", self.offset_from_sync, self.period, other.offset_from_sync, other.period, x1);
-                return x1 - self.offset_from_sync;
-            }
-        }
+    let (mut a, mut b, mut result) = (a % modulus, b, 0u128);
+    while b > 0 {
+        if b & 1 == 1 { result = (result + a) % modulus; }
+        a = (a << 1) % modulus;
+        b >>= 1;
     }
 
-    fn reduce(&self, other: &PeriodicWithOffsets) -> PeriodicWithOffsets {
-        let lcm = num::integer::lcm(self.period, other.period);
-        let offset = self.find_first_sync(other);
+    result
+}
+
+impl Congruence {
+    /// Merges two congruences into one that holds exactly when both do. The moduli need not be
+    /// coprime, but then the remainders must agree modulo their gcd.
+    fn combine(&self, other: &Congruence) -> Result<Congruence, CrtError> {
+        let limit = i128::MAX as u128;
+        if self.modulus > limit || other.modulus > limit {
+            return Err(CrtError::Overflow);
+        }
+
+        let (g, inverse, _) = extended_gcd(self.modulus as i128, other.modulus as i128);
+        let g = g as u128;
+        let difference = (other.remainder + other.modulus - self.remainder % other.modulus) % other.modulus;
+
+        if !difference.is_multiple_of(g) {
+            return Err(CrtError::NoSolution(*self, *other));
+        }
+
+        let reduced = other.modulus / g;
+        let modulus = (self.modulus / g).checked_mul(other.modulus)
+            .filter(|modulus| *modulus <= limit)
+            .ok_or(CrtError::Overflow)?;
+        let inverse = inverse.rem_euclid(reduced as i128) as u128;
+        let k = mul_mod(difference / g, inverse, reduced);
+        let remainder = (self.remainder + mul_mod(self.modulus, k, modulus)) % modulus;
 
-        PeriodicWithOffsets { period: lcm, offset_from_sync: 0, first_sync: offset }
+        Ok(Congruence { remainder, modulus })
     }
 }
 
+fn solve_crt(congruences: &[Congruence]) -> Result<Congruence, CrtError> {
+    congruences.iter()
+        .try_fold(Congruence { remainder: 0, modulus: 1 }, |combined, next| combined.combine(next))
+}
+
 fn parse_input_part_one(path: &str) -> (u32, Vec<u32>) {
     let input = std::fs::read_to_string(path).unwrap();
     let mut lines = input.lines();
@@ -53,7 +107,7 @@ fn parse_input_part_one(path: &str) -> (u32, Vec<u32>) {
     (timestamp, buses)
 }
 
-fn parse_input_part_two(path: &str) -> Vec<PeriodicWithOffsets> {
+fn parse_input_part_two(path: &str) -> Vec<Bus> {
     let input = std::fs::read_to_string(path).unwrap();
     let mut lines = input.lines();
     lines.next();
@@ -64,11 +118,11 @@ fn parse_input_part_two(path: &str) -> Vec<PeriodicWithOffsets> {
         .split(',')
         .enumerate()
         .filter(|(_, bus)| *bus != "x")
-        .map(|(x, bus)| PeriodicWithOffsets { first_sync: 0, offset_from_sync: x as u64, period: bus.parse().unwrap() })
+        .map(|(x, bus)| Bus { offset: x as u64, period: bus.parse().unwrap() })
         .collect()
 }
 
-fn soonest_arriving_after(timestamp: u32, buses: &Vec<u32>) -> (u32, u32) {
+fn soonest_arriving_after(timestamp: u32, buses: &[u32]) -> (u32, u32) {
     buses
         .iter()
         .map(|bus| (*bus, ((timestamp / *bus) + 1) * *bus))
@@ -76,35 +130,94 @@ fn soonest_arriving_after(timestamp: u32, buses: &Vec<u32>) -> (u32, u32) {
         .unwrap()
 }
 
-fn find_magic_time(periodics: &Vec<PeriodicWithOffsets>) -> u64 {
-    let mut reduced = periodics.clone();
+/// The earliest timestamp at which every bus departs at its offset.
+fn find_magic_time(buses: &[Bus]) -> Result<u128, CrtError> {
+    let congruences: Vec<Congruence> = buses.iter().map(Bus::congruence).collect();
+    solve_crt(&congruences).map(|solution| solution.remainder)
+}
 
-    while reduced.len() > 1 {
-        println!("{:?}", reduced);
+const USAGE: &str = "usage: day-13 [--board] [--next <bus>] [--count <n>] [--window <minutes>] [--align <bus,bus,...>] [input]";
 
-        reduced = reduced
-            .chunks(2)
-            .map(|periodic_slice|
-                match periodic_slice {
-                    &[a, b] => a.reduce(&b),
-                    &[a] => a,
-                    _ => panic!()
-                })
-            .collect();
-    }
+/// `--board` prints the departure board from the note's timestamp to the first departure.
+/// `--next` lists a bus's next `count` departures after the timestamp, `--window` every departure
+/// in the following minutes, and `--align` the next time the chosen buses leave at their offsets.
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut board = false;
+    let (mut next, mut count, mut window) = (None, 5, None);
+    let mut align: Option<Vec<u64>> = None;
+    let mut input = String::from("input.txt");
 
-    reduced[0].first_sync
-}
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--board" => board = true,
+            "--next" | "--count" | "--window" => {
+                let n = match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => n,
+                    None => return eprintln!("{} needs a number\n{}", arg, USAGE)
+                };
+
+                match arg.as_str() {
+                    "--next" => next = Some(n),
+                    "--count" => count = n as usize,
+                    _ => window = Some(n)
+                }
+            },
+            "--align" => match args.next().and_then(|list| list.split(',').map(|bus| bus.parse().ok()).collect()) {
+                Some(buses) => align = Some(buses),
+                None => return eprintln!("--align needs a comma separated list of buses\n{}", USAGE)
+            },
+            option if option.starts_with("--") => return eprintln!("unknown option {}\n{}", option, USAGE),
+            _ => input = arg
+        }
+    }
 
-fn main() {
     // Part one
-    let (timestamp, buses) = parse_input_part_one("input.txt");
+    let (timestamp, buses) = parse_input_part_one(&input);
     let (bus, arrival) = soonest_arriving_after(timestamp, &buses);
     println!("Part one: bus {} arrives {} minutes late", bus, arrival - timestamp);
 
+    let buses = parse_input_part_two(&input);
+    let timetable = Timetable::new(buses.clone());
+    let timestamp = timestamp as u64;
+
+    if board {
+        print!("{}", timetable.board(timestamp, arrival as u64));
+    }
+
+    if let Some(period) = next {
+        match timetable.next_departures(period, timestamp, count) {
+            Ok(departures) => println!("Bus {} departs at {:?}", period, departures),
+            Err(e) => println!("{:?}", e)
+        }
+    }
+
+    if let Some(minutes) = window {
+        for (time, period) in timetable.departures_between(timestamp, timestamp + minutes) {
+            println!("{}: bus {}", time, period);
+        }
+    }
+
+    if let Some(periods) = align {
+        let offsets: Vec<(u64, u64)> = periods.iter()
+            .map(|period| (*period, buses.iter().find(|bus| bus.period == *period).map_or(0, |bus| bus.offset)))
+            .collect();
+
+        match timetable.next_alignment(timestamp, &offsets) {
+            Ok(time) => println!("Buses {:?} next align at {}", periods, time),
+            Err(e) => println!("{:?}", e)
+        }
+    }
+
     // Part two
-    let buses = parse_input_part_two("input.txt");
-    println!("{:?}", find_magic_time(&buses))
+    let start = Instant::now();
+    let magic_time = find_magic_time(&buses);
+    let elapsed = start.elapsed();
+
+    match magic_time {
+        Ok(time) => println!("Part two: {} (solved in {}µs)", time, elapsed.as_micros()),
+        Err(e) => println!("Part two has no answer: {:?}", e)
+    }
 }
 
 #[test]
@@ -118,14 +231,40 @@ fn test_part_one() {
 #[test]
 fn test_part_two() {
     let buses = parse_input_part_two("example.txt");
-    assert_eq!(find_magic_time(&buses), 1068781);
+    assert_eq!(find_magic_time(&buses), Ok(1068781));
 
     let buses = parse_input_part_two("example2.txt");
-    assert_eq!(find_magic_time(&buses), 3417);
+    assert_eq!(find_magic_time(&buses), Ok(3417));
 
     let buses = parse_input_part_two("example3.txt");
-    assert_eq!(find_magic_time(&buses), 1202161486);
+    assert_eq!(find_magic_time(&buses), Ok(1202161486));
 
     let buses = parse_input_part_two("example4.txt");
-    assert_eq!(find_magic_time(&buses), 1261476);
-}
\ No newline at end of file
+    assert_eq!(find_magic_time(&buses), Ok(1261476));
+}
+
+#[test]
+fn test_non_coprime_periods() {
+    let a = Congruence { remainder: 2, modulus: 6 };
+    let b = Congruence { remainder: 4, modulus: 8 };
+    assert_eq!(a.combine(&b), Ok(Congruence { remainder: 20, modulus: 24 }));
+
+    let c = Congruence { remainder: 3, modulus: 8 };
+    assert_eq!(a.combine(&c), Err(CrtError::NoSolution(a, c)));
+
+    // Buses every 4 and 6 minutes can never leave one minute apart
+    let buses = vec![Bus { period: 4, offset: 0 }, Bus { period: 6, offset: 1 }];
+    assert!(find_magic_time(&buses).is_err());
+}
+
+#[test]
+fn test_large_moduli() {
+    let a = Congruence { remainder: 1, modulus: (1 << 61) - 1 };
+    let b = Congruence { remainder: 2, modulus: (1 << 31) - 1 };
+    let combined = a.combine(&b).unwrap();
+
+    assert_eq!(combined.remainder % a.modulus, 1);
+    assert_eq!(combined.remainder % b.modulus, 2);
+    assert_eq!(a.combine(&Congruence { remainder: 0, modulus: (1 << 89) - 1 }), Err(CrtError::Overflow));
+    assert_eq!(a.combine(&Congruence { remainder: 0, modulus: u128::MAX }), Err(CrtError::Overflow));
+}
@@ -0,0 +1,137 @@
+use super::{solve_crt, Bus, Congruence, CrtError};
+
+#[derive(Debug, PartialEq)]
+pub enum TimetableError {
+    UnknownBus(u64),
+    Crt(CrtError)
+}
+
+/// The buses in service, in the order the schedule lists them.
+pub struct Timetable {
+    pub buses: Vec<Bus>
+}
+
+impl Timetable {
+    pub fn new(buses: Vec<Bus>) -> Self {
+        Timetable { buses }
+    }
+
+    fn check(&self, period: u64) -> Result<(), TimetableError> {
+        if self.buses.iter().any(|bus| bus.period == period) {
+            Ok(())
+        } else {
+            Err(TimetableError::UnknownBus(period))
+        }
+    }
+
+    /// The next `count` departures of a bus strictly after `time`.
+    pub fn next_departures(&self, period: u64, time: u64, count: usize) -> Result<Vec<u64>, TimetableError> {
+        self.check(period)?;
+
+        let first = (time / period + 1) * period;
+        Ok((0..count as u64).map(|n| first + n * period).collect())
+    }
+
+    /// Every `(time, bus)` departure from `start` to `end` inclusive, in time order. Buses leaving
+    /// at the same minute keep their schedule order.
+    pub fn departures_between(&self, start: u64, end: u64) -> Vec<(u64, u64)> {
+        let mut departures: Vec<(u64, u64)> = self.buses.iter()
+            .flat_map(|bus| {
+                let first = start.div_ceil(bus.period) * bus.period;
+                (first..=end).step_by(bus.period as usize).map(move |time| (time, bus.period))
+            })
+            .collect();
+
+        departures.sort_by_key(|(time, _)| *time);
+        departures
+    }
+
+    /// The earliest time at or after `time` when every `(bus, offset)` pair has its bus departing
+    /// `offset` minutes later.
+    pub fn next_alignment(&self, time: u64, offsets: &[(u64, u64)]) -> Result<u128, TimetableError> {
+        let congruences = offsets.iter()
+            .map(|(period, offset)| {
+                self.check(*period)?;
+                Ok(Bus { period: *period, offset: *offset }.congruence())
+            })
+            .collect::<Result<Vec<Congruence>, TimetableError>>()?;
+
+        let solution = solve_crt(&congruences).map_err(TimetableError::Crt)?;
+        let time = time as u128;
+
+        if time <= solution.remainder {
+            Ok(solution.remainder)
+        } else {
+            Ok(solution.remainder + (time - solution.remainder).div_ceil(solution.modulus) * solution.modulus)
+        }
+    }
+
+    /// A table like the puzzle's, one row per minute with a `D` under each bus that departs.
+    pub fn board(&self, start: u64, end: u64) -> String {
+        let time_width = end.to_string().len().max(4) + 4;
+        let labels: Vec<String> = self.buses.iter().map(|bus| format!("bus {}", bus.period)).collect();
+
+        let mut board = format!("{:<width$}", "time", width = time_width);
+        for label in &labels {
+            board.push_str(&format!("{:<width$}", label, width = label.len() + 3));
+        }
+        board = board.trim_end().to_string();
+        board.push('\n');
+
+        for time in start..=end {
+            let mut row = format!("{:<width$}", time, width = time_width);
+            for (bus, label) in self.buses.iter().zip(&labels) {
+                let symbol = if time.is_multiple_of(bus.period) { "D" } else { "." };
+                row.push_str(&format!("{:^width$}   ", symbol, width = label.len()));
+            }
+
+            board.push_str(row.trim_end());
+            board.push('\n');
+        }
+
+        board
+    }
+}
+
+#[test]
+fn test_next_departures() {
+    let timetable = Timetable::new(super::parse_input_part_two("example.txt"));
+
+    assert_eq!(timetable.next_departures(59, 939, 3), Ok(vec![944, 1003, 1062]));
+    assert_eq!(timetable.next_departures(7, 945, 2), Ok(vec![952, 959]));
+    assert_eq!(timetable.next_departures(11, 939, 1), Err(TimetableError::UnknownBus(11)));
+}
+
+#[test]
+fn test_departures_between() {
+    let timetable = Timetable::new(super::parse_input_part_two("example.txt"));
+
+    assert_eq!(timetable.departures_between(939, 945), vec![(944, 59), (945, 7)]);
+    assert_eq!(timetable.departures_between(1068781, 1068781), vec![(1068781, 7)]);
+}
+
+#[test]
+fn test_next_alignment() {
+    let timetable = Timetable::new(super::parse_input_part_two("example.txt"));
+    let all: Vec<(u64, u64)> = timetable.buses.iter().map(|bus| (bus.period, bus.offset)).collect();
+
+    assert_eq!(timetable.next_alignment(0, &all), Ok(1068781));
+    assert_eq!(timetable.next_alignment(1068782, &all), Ok(1068781 + 7 * 13 * 59 * 31 * 19));
+
+    // Bus 7 leaves at 7, 14, 21 ... and bus 13 one minute after it at 78
+    assert_eq!(timetable.next_alignment(0, &[(7, 0), (13, 1)]), Ok(77));
+    assert_eq!(timetable.next_alignment(78, &[(7, 0), (13, 1)]), Ok(77 + 91));
+    assert_eq!(timetable.next_alignment(0, &[(8, 0)]), Err(TimetableError::UnknownBus(8)));
+}
+
+#[test]
+fn test_board() {
+    let timetable = Timetable::new(super::parse_input_part_two("example.txt"));
+    let board = timetable.board(1068780, 1068782);
+    let lines: Vec<&str> = board.lines().collect();
+
+    assert_eq!(lines[0], "time       bus 7   bus 13   bus 59   bus 31   bus 19");
+    assert_eq!(lines[1], "1068780      .       .        .        .        .");
+    assert_eq!(lines[2], "1068781      D       .        .        .        .");
+    assert_eq!(lines[3], "1068782      .       D        .        .        .");
+}
@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+
+/// The most addresses a dump will expand floating writes into.
+pub const LIMIT: u128 = 1 << 20;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+    /// `mem[<address>] = <value>` lines, like the program's own writes.
+    Text,
+    /// A JSON array with one `{"address": .., "value": ..}` object per line.
+    Json
+}
+
+/// Every written address and its value, sorted by address so two runs can be diffed.
+pub fn dump(memory: &HashMap<u64, u64>, format: Format) -> String {
+    let mut entries: Vec<(&u64, &u64)> = memory.iter().collect();
+    entries.sort();
+
+    let lines: Vec<String> = entries.iter()
+        .map(|(address, value)| match format {
+            Format::Text => format!("mem[{}] = {}", address, value),
+            Format::Json => format!("  {{\"address\": {}, \"value\": {}}}", address, value)
+        })
+        .collect();
+
+    match format {
+        Format::Text if lines.is_empty() => String::new(),
+        Format::Text => format!("{}\n", lines.join("\n")),
+        Format::Json if lines.is_empty() => "[]\n".to_string(),
+        Format::Json => format!("[\n{}\n]\n", lines.join(",\n"))
+    }
+}
+
+#[test]
+fn test_dump() {
+    let memory: HashMap<u64, u64> = vec![(8, 64), (7, 101)].into_iter().collect();
+
+    assert_eq!(dump(&memory, Format::Text), "mem[7] = 101\nmem[8] = 64\n");
+    assert_eq!(
+        dump(&memory, Format::Json),
+        "[\n  {\"address\": 7, \"value\": 101},\n  {\"address\": 8, \"value\": 64}\n]\n"
+    );
+    assert_eq!(dump(&HashMap::new(), Format::Json), "[]\n");
+}
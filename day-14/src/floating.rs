@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+
+use super::Memory;
+
+/// A set of addresses: bits in `floating` may be either value, every other bit is taken from `fixed`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Pattern {
+    pub fixed: u64,
+    pub floating: u64
+}
+
+impl Pattern {
+    pub fn new(fixed: u64, floating: u64) -> Self {
+        Pattern { fixed: fixed & !floating, floating }
+    }
+
+    pub fn single(address: u64) -> Self {
+        Pattern { fixed: address, floating: 0 }
+    }
+
+    /// How many addresses match, which is 2^64 when every bit floats.
+    pub fn size(&self) -> u128 {
+        1u128 << self.floating.count_ones()
+    }
+
+    pub fn intersects(&self, other: &Pattern) -> bool {
+        let both_fixed = !(self.floating | other.floating);
+        (self.fixed ^ other.fixed) & both_fixed == 0
+    }
+
+    /// The addresses matching `self` but not `other`, as disjoint patterns. Each bit that floats
+    /// here but is fixed in `other` splits off the half that disagrees with `other`.
+    pub fn subtract(&self, other: &Pattern) -> Vec<Pattern> {
+        if !self.intersects(other) {
+            return vec![*self];
+        }
+
+        let mut remaining = *self;
+        let mut pieces = Vec::new();
+        let splits = self.floating & !other.floating;
+
+        for bit in (0..64).map(|i| 1u64 << i).filter(|bit| splits & bit != 0) {
+            remaining.floating &= !bit;
+            pieces.push(Pattern { fixed: remaining.fixed | (!other.fixed & bit), floating: remaining.floating });
+            remaining.fixed |= other.fixed & bit;
+        }
+
+        pieces
+    }
+
+    /// Every concrete address, walking the subsets of the floating bits.
+    pub fn addresses(&self) -> impl Iterator<Item = u64> {
+        let (fixed, floating) = (self.fixed, self.floating);
+        let mut subset = Some(0u64);
+
+        std::iter::from_fn(move || {
+            let current = subset?;
+            let next = current.wrapping_sub(floating) & floating;
+            subset = if next == 0 { None } else { Some(next) };
+            Some(fixed | current)
+        })
+    }
+}
+
+/// Memory that keeps each write as a pattern. Earlier writes have later ones subtracted from them,
+/// so every address is covered by at most one live write and sums never expand the patterns.
+#[derive(Debug, Default)]
+pub struct FloatingMemory {
+    pub writes: Vec<(Pattern, u64)>
+}
+
+impl FloatingMemory {
+    /// The number of distinct addresses written.
+    pub fn len(&self) -> u128 {
+        self.writes.iter().map(|(pattern, _)| pattern.size()).sum()
+    }
+
+    /// Expands into concrete addresses, or `None` if there would be more than `limit` of them.
+    pub fn materialize(&self, limit: u128) -> Option<HashMap<u64, u64>> {
+        if self.len() > limit {
+            return None;
+        }
+
+        Some(self.writes.iter()
+            .flat_map(|(pattern, value)| pattern.addresses().map(move |address| (address, *value)))
+            .collect())
+    }
+}
+
+impl Memory for FloatingMemory {
+    fn store(&mut self, addresses: Pattern, value: u64) {
+        self.writes = self.writes.iter()
+            .flat_map(|(pattern, old)| pattern.subtract(&addresses).into_iter().map(move |piece| (piece, *old)))
+            .collect();
+
+        self.writes.push((addresses, value));
+    }
+
+    fn sum(&self) -> u128 {
+        self.writes.iter().map(|(pattern, value)| pattern.size() * *value as u128).sum()
+    }
+}
+
+#[test]
+fn test_pattern_addresses() {
+    let pattern = Pattern::new(0b11010, 0b100001);
+    let addresses: Vec<u64> = pattern.addresses().collect();
+
+    assert_eq!(addresses, vec![26, 27, 58, 59]);
+    assert_eq!(pattern.size(), 4);
+    assert_eq!(Pattern::single(7).addresses().collect::<Vec<u64>>(), vec![7]);
+}
+
+#[test]
+fn test_subtract() {
+    // 0XX minus 01X leaves 00X
+    let a = Pattern::new(0b000, 0b011);
+    let b = Pattern::new(0b010, 0b001);
+    assert_eq!(a.subtract(&b), vec![Pattern::new(0b000, 0b001)]);
+
+    // XX minus 11 leaves X0 and 01
+    let a = Pattern::new(0, 0b11);
+    let pieces = a.subtract(&Pattern::single(0b11));
+    assert_eq!(pieces.iter().map(Pattern::size).sum::<u128>(), 3);
+    assert!(pieces.iter().all(|piece| !piece.intersects(&Pattern::single(0b11))));
+
+    assert_eq!(a.subtract(&Pattern::single(0b100)), vec![a]);
+    assert_eq!(Pattern::single(1).subtract(&a), vec![]);
+}
+
+#[test]
+fn test_floating_memory() {
+    let mut memory = FloatingMemory::default();
+    memory.store(Pattern::new(0, 0b1111), 3);
+    memory.store(Pattern::new(0b0100, 0b0011), 5);
+    memory.store(Pattern::single(0b0101), 1);
+
+    assert_eq!(memory.len(), 16);
+    assert_eq!(memory.sum(), 12 * 3 + 3 * 5 + 1);
+
+    let concrete = memory.materialize(16).unwrap();
+    assert_eq!(concrete.len(), 16);
+    assert_eq!((concrete[&0b0101], concrete[&0b0110], concrete[&0b1000]), (1, 5, 3));
+    assert!(memory.materialize(15).is_none());
+
+    // All 64 bits floating is still a single pattern
+    memory.store(Pattern::new(0, u64::MAX), 1);
+    assert_eq!(memory.sum(), 1 << 64);
+}
@@ -1,5 +1,48 @@
+mod dump;
+mod floating;
+
 use std::collections::HashMap;
-use regex::Regex;
+
+use dump::Format;
+use floating::{FloatingMemory, Pattern};
+
+/// The bits of a mask line, split by the character that set them.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct Bitmask {
+    ones: u64,
+    zeros: u64,
+    floating: u64
+}
+
+impl Bitmask {
+    /// Parses a mask whose first character is the highest bit. The width is the length of the mask
+    /// unless given, in which case the mask must be exactly that long.
+    fn parse(input: &str, width: Option<usize>) -> Result<Self, ParseError> {
+        let length = input.chars().count();
+        let width = width.unwrap_or(length);
+
+        if width > 64 {
+            return Err(ParseError::MaskTooWide(width));
+        }
+        if length != width {
+            return Err(ParseError::MaskWidthMismatch { expected: width, found: length });
+        }
+
+        let mut bitmask = Bitmask::default();
+        for (index, c) in input.chars().enumerate() {
+            let bit = 1u64 << (width - 1 - index);
+
+            match c {
+                '1' => bitmask.ones |= bit,
+                '0' => bitmask.zeros |= bit,
+                'X' => bitmask.floating |= bit,
+                other => return Err(ParseError::InvalidMaskCharacter(other))
+            }
+        }
+
+        Ok(bitmask)
+    }
+}
 
 struct Mask {
     or_value: u64,
@@ -12,145 +55,241 @@ impl Mask {
     }
 }
 
-impl From<&str> for Mask {
-    fn from(input: &str) -> Self {
-        let mut and_value = 0xFFFF_FFFF_FFFF_FFFF;
-        let mut or_value  = 0x0000_0000_0000_0000;
-
-        input.chars().enumerate().for_each(|(index, c)| match c {
-            '1' => { or_value = or_value | (1u64 << (35 - index)); },
-            '0'=> { and_value = and_value & !(1u64 << (35 - index)); },
-            _ => ()
-        });
-
-        Mask { or_value, and_value }
+impl From<Bitmask> for Mask {
+    fn from(bitmask: Bitmask) -> Self {
+        Mask { or_value: bitmask.ones, and_value: !bitmask.zeros }
     }
 }
 
-fn run_bitmask_program(path: &str) -> HashMap<u64, u64> {
-    let mut memory = HashMap::new();
-    let mut current_mask = Mask::from("");
-    let mem_regex = Regex::new(r"mem\[(\d+)\] = (\d+)").unwrap();
+#[derive(Debug, PartialEq)]
+enum Instruction {
+    SetMask(Bitmask),
+    Write { address: u64, value: u64 }
+}
 
-    for line in std::fs::read_to_string(path).unwrap().lines() {
-        if &line[0..4] == "mask" {
-            current_mask = Mask::from(&line[7..]);
-        } else {
-            let captures = mem_regex.captures(line).unwrap();
-            let memory_address = captures[1].parse().unwrap();
-            let value = captures[2].parse().unwrap();
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    UnknownInstruction(String),
+    InvalidWrite(String),
+    InvalidMaskCharacter(char),
+    MaskTooWide(usize),
+    MaskWidthMismatch { expected: usize, found: usize }
+}
 
-            memory.insert(memory_address, current_mask.apply_to(value));
+impl Instruction {
+    /// `mask = <bits>` or `mem[<address>] = <value>`, with masks checked against `width` if given.
+    fn parse(line: &str, width: Option<usize>) -> Result<Self, ParseError> {
+        if let Some(mask) = line.strip_prefix("mask = ") {
+            return Ok(Instruction::SetMask(Bitmask::parse(mask, width)?));
         }
-    }
 
-    memory
+        let write = line.strip_prefix("mem[").ok_or_else(|| ParseError::UnknownInstruction(line.to_string()))?;
+        let invalid = || ParseError::InvalidWrite(line.to_string());
+        let (address, value) = write.split_once("] = ").ok_or_else(invalid)?;
+
+        Ok(Instruction::Write {
+            address: address.parse().map_err(|_| invalid())?,
+            value: value.parse().map_err(|_| invalid())?
+        })
+    }
 }
 
-struct MemoryMask {
-    floating_indeces: Vec<usize>,
-    mask: u64
+/// Where the emulator stores writes. Each write goes to every address matching a pattern.
+trait Memory {
+    fn store(&mut self, addresses: Pattern, value: u64);
+    fn sum(&self) -> u128;
 }
 
-impl MemoryMask {
-    fn floating_iter<'a>(&'a self, base: u64) -> FloatingIterator {
-        FloatingIterator {
-            floating_indeces: &self.floating_indeces,
-            iterator: 0,
-            base: base | self.mask
+/// Concrete memory, one entry per address.
+impl Memory for HashMap<u64, u64> {
+    fn store(&mut self, addresses: Pattern, value: u64) {
+        for address in addresses.addresses() {
+            self.insert(address, value);
         }
     }
-}
 
-struct FloatingIterator<'a> {
-    floating_indeces: &'a Vec<usize>,
-    iterator: usize,
-    base: u64
+    fn sum(&self) -> u128 {
+        self.values().map(|value| *value as u128).sum()
+    }
 }
 
-impl <'a> Iterator for FloatingIterator<'a> {
-    type Item = u64;
+/// How a mask changes what a write does to memory.
+trait Decoder {
+    fn set_mask(&mut self, bitmask: Bitmask);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.iterator == (1 << self.floating_indeces.len()) { return None; }
+    /// The addresses to write and the value to write to them.
+    fn decode(&self, address: u64, value: u64) -> (Pattern, u64);
+}
 
-        let mut output = self.base;
-        for (position_index, floating_index) in self.floating_indeces.iter().enumerate() {
-            let shifted_bit = 1u64 << floating_index;
+/// Version 1: the mask overwrites bits of the value.
+struct ValueDecoder {
+    mask: Mask
+}
 
-            output = match self.iterator & (1 << position_index) {
-                0 => output & !shifted_bit,
-                _ => output | shifted_bit
-            }
+impl ValueDecoder {
+    fn new() -> Self {
+        ValueDecoder { mask: Mask::from(Bitmask::default()) }
+    }
+}
 
-        }
+impl Decoder for ValueDecoder {
+    fn set_mask(&mut self, bitmask: Bitmask) {
+        self.mask = Mask::from(bitmask);
+    }
 
-        self.iterator += 1;
-        Some(output)
+    fn decode(&self, address: u64, value: u64) -> (Pattern, u64) {
+        (Pattern::single(address), self.mask.apply_to(value))
     }
 }
 
-impl From<&str> for MemoryMask {
-    fn from(input: &str) -> Self {
-        let mut mask  = 0x0000_0000_0000_0000;
-        let mut floating_indeces = Vec::new();
+/// Version 2: the mask sets bits of the address, and floating bits write to every combination.
+struct AddressDecoder {
+    bitmask: Bitmask
+}
+
+impl AddressDecoder {
+    fn new() -> Self {
+        AddressDecoder { bitmask: Bitmask::default() }
+    }
+}
 
-        input.chars().enumerate().for_each(|(index, c)| match c {
-            '1' => { mask = mask | (1u64 << (35 - index)); },
-            'X'=> { floating_indeces.push(35 - index) },
-            _ => ()
-        });
+impl Decoder for AddressDecoder {
+    fn set_mask(&mut self, bitmask: Bitmask) {
+        self.bitmask = bitmask;
+    }
 
-        MemoryMask { floating_indeces, mask }
+    fn decode(&self, address: u64, value: u64) -> (Pattern, u64) {
+        (Pattern::new(address | self.bitmask.ones, self.bitmask.floating), value)
     }
 }
 
+struct DockingProgram {
+    instructions: Vec<Instruction>
+}
 
-fn run_memory_address_program(path: &str) -> HashMap<u64, u64> {
-    let mut memory = HashMap::new();
-    let mut current_mask = MemoryMask::from("");
-    let mem_regex = Regex::new(r"mem\[(\d+)\] = (\d+)").unwrap();
+impl DockingProgram {
+    /// Loads a program whose masks are `width` bits, or as long as each mask string when `None`.
+    fn load(path: &str, width: Option<usize>) -> Result<Self, ParseError> {
+        let instructions = std::fs::read_to_string(path).unwrap()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| Instruction::parse(line, width))
+            .collect::<Result<Vec<Instruction>, ParseError>>()?;
 
-    for line in std::fs::read_to_string(path).unwrap().lines() {
-        if &line[0..4] == "mask" {
-            current_mask = MemoryMask::from(&line[7..]);
-        } else {
-            let captures = mem_regex.captures(line).unwrap();
-            let memory_address = captures[1].parse().unwrap();
-            let value = captures[2].parse().unwrap();
+        Ok(DockingProgram { instructions })
+    }
 
-            for potential in current_mask.floating_iter(memory_address) {
-                memory.insert(potential, value);
+    fn run<D: Decoder, M: Memory + Default>(&self, decoder: &mut D) -> M {
+        let mut memory = M::default();
+
+        for instruction in &self.instructions {
+            match instruction {
+                Instruction::SetMask(bitmask) => decoder.set_mask(*bitmask),
+                Instruction::Write { address, value } => {
+                    let (addresses, value) = decoder.decode(*address, *value);
+                    memory.store(addresses, value);
+                }
             }
         }
-    }
 
-    memory
+        memory
+    }
 }
 
+const USAGE: &str = "usage: day-14 [--width <bits>] [--dump <v1|v2>] [--json] [input]";
+
+/// With `--dump`, prints every address the chosen decoder wrote instead of the answers.
 fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut width = None;
+    let mut dump_version = None;
+    let mut format = Format::Text;
+    let mut input = String::from("input.txt");
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => match args.next().and_then(|bits| bits.parse().ok()) {
+                Some(bits) => width = Some(bits),
+                None => return eprintln!("--width needs a number of bits\n{}", USAGE)
+            },
+            "--dump" => match args.next() {
+                Some(version) => dump_version = Some(version),
+                None => return eprintln!("--dump needs a decoder version\n{}", USAGE)
+            },
+            "--json" => format = Format::Json,
+            option if option.starts_with("--") => return eprintln!("unknown option {}\n{}", option, USAGE),
+            _ => input = arg
+        }
+    }
+
+    let program = match DockingProgram::load(&input, width) {
+        Ok(program) => program,
+        Err(e) => return println!("Invalid program: {:?}", e)
+    };
+
+    if let Some(version) = dump_version {
+        let memory: Option<HashMap<u64, u64>> = match version.as_str() {
+            "v1" => Some(program.run(&mut ValueDecoder::new())),
+            "v2" => program.run::<_, FloatingMemory>(&mut AddressDecoder::new()).materialize(dump::LIMIT),
+            other => return println!("Unknown decoder {}", other)
+        };
+
+        match memory {
+            Some(memory) => print!("{}", dump::dump(&memory, format)),
+            None => println!("More than {} addresses were written", dump::LIMIT)
+        }
+        return;
+    }
+
     // Part One
-    let output = run_bitmask_program("input.txt");
-    println!("Part 1: {}", output.values().sum::<u64>());
+    let output: HashMap<u64, u64> = program.run(&mut ValueDecoder::new());
+    println!("Part 1: {}", output.sum());
 
     // Part Two
-    let output = run_memory_address_program("input.txt");
-    println!("Part 2: {}", output.values().sum::<u64>());
+    let output: FloatingMemory = program.run(&mut AddressDecoder::new());
+    println!("Part 2: {} ({} addresses in {} patterns)", output.sum(), output.len(), output.writes.len());
 }
 
 #[test]
 fn test_part_one() {
-    let mask = Mask::from("XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X");
+    let mask = Mask::from(Bitmask::parse("XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X", Some(36)).unwrap());
     assert_eq!(mask.or_value, 0b1000000);
     assert_eq!(mask.and_value, 0xFFFF_FFFF_FFFF_FFFD);
     assert_eq!(mask.apply_to(11), 73);
 
-    let output = run_bitmask_program("example1.txt");
+    let output: HashMap<u64, u64> = DockingProgram::load("example1.txt", None).unwrap().run(&mut ValueDecoder::new());
     assert_eq!(output.values().sum::<u64>(), 165);
 }
 
 #[test]
 fn test_part_two() {
-    let output = run_memory_address_program("example2.txt");
+    let program = DockingProgram::load("example2.txt", None).unwrap();
+    let output: HashMap<u64, u64> = program.run(&mut AddressDecoder::new());
     assert_eq!(output.values().sum::<u64>(), 208);
+
+    let output: FloatingMemory = program.run(&mut AddressDecoder::new());
+    assert_eq!(output.sum(), 208);
+    assert_eq!(output.len(), 10);
+    assert_eq!(output.materialize(10), Some(program.run(&mut AddressDecoder::new())));
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(Instruction::parse("mem[8] = 11", None), Ok(Instruction::Write { address: 8, value: 11 }));
+    assert_eq!(Instruction::parse("ma", None), Err(ParseError::UnknownInstruction("ma".to_string())));
+    assert_eq!(Instruction::parse("mem[8 = 11", None), Err(ParseError::InvalidWrite("mem[8 = 11".to_string())));
+    assert_eq!(Instruction::parse("mem[8] = -1", None), Err(ParseError::InvalidWrite("mem[8] = -1".to_string())));
+}
+
+#[test]
+fn test_mask_width() {
+    let bitmask = Bitmask::parse("1X0", None).unwrap();
+    assert_eq!(bitmask, Bitmask { ones: 0b100, zeros: 0b001, floating: 0b010 });
+    assert_eq!(Mask::from(bitmask).apply_to(0b1011), 0b1110);
+
+    let wide = "1".repeat(64);
+    assert_eq!(Bitmask::parse(&wide, Some(64)).unwrap().ones, u64::MAX);
+    assert_eq!(Bitmask::parse(&"X".repeat(65), None), Err(ParseError::MaskTooWide(65)));
+    assert_eq!(Bitmask::parse("1X0", Some(36)), Err(ParseError::MaskWidthMismatch { expected: 36, found: 3 }));
+    assert_eq!(Instruction::parse("mask = 10x", None), Err(ParseError::InvalidMaskCharacter('x')));
 }
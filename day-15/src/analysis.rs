@@ -0,0 +1,131 @@
+use super::MemoryGame;
+
+/// The running statistics as of one turn.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sample {
+    pub turn: usize,
+    pub distinct: usize,
+    pub longest_gap: usize,
+    pub zeros: usize
+}
+
+/// Statistics gathered while a game runs, sampled every `interval` turns.
+pub struct Analysis {
+    pub current: Sample,
+    pub samples: Vec<Sample>,
+    /// The turn each number below the limit was first spoken on, if it has been.
+    pub first_spoken: Vec<Option<usize>>,
+    interval: usize,
+    /// How many opening turns are starters, which are chosen rather than gaps.
+    starters: usize,
+    seen: Vec<bool>
+}
+
+impl Analysis {
+    pub fn new(interval: usize, first_spoken_limit: usize) -> Self {
+        Analysis {
+            current: Sample { turn: 0, distinct: 0, longest_gap: 0, zeros: 0 },
+            samples: Vec::new(),
+            first_spoken: vec![None; first_spoken_limit],
+            interval,
+            starters: 0,
+            seen: Vec::new()
+        }
+    }
+
+    /// Plays `turns` turns of a game, recording every number spoken.
+    pub fn run(starters: &[usize], turns: usize, interval: usize, first_spoken_limit: usize) -> Self {
+        let mut analysis = Analysis::new(interval, first_spoken_limit);
+        analysis.starters = starters.len();
+        analysis.seen.reserve(turns);
+
+        for number in MemoryGame::spoken(starters).take(turns) {
+            analysis.record(number);
+        }
+
+        analysis
+    }
+
+    /// Records the number spoken on the next turn. After the starters, each number is the gap since
+    /// the previous number was last spoken, so the largest is the longest gap.
+    pub fn record(&mut self, number: usize) {
+        let turn = self.current.turn + 1;
+        self.current.turn = turn;
+
+        if number >= self.seen.len() {
+            self.seen.resize(number + 1, false);
+        }
+        if !self.seen[number] {
+            self.seen[number] = true;
+            self.current.distinct += 1;
+
+            if let Some(first) = self.first_spoken.get_mut(number) {
+                *first = Some(turn);
+            }
+        }
+
+        if number == 0 {
+            self.current.zeros += 1;
+        }
+        if turn > self.starters {
+            self.current.longest_gap = self.current.longest_gap.max(number);
+        }
+
+        if self.interval > 0 && turn.is_multiple_of(self.interval) {
+            self.samples.push(self.current);
+        }
+    }
+
+    pub fn zero_frequency(&self) -> f64 {
+        self.current.zeros as f64 / self.current.turn.max(1) as f64
+    }
+
+    pub fn samples_csv(&self) -> String {
+        let mut csv = String::from("turn,distinct,longest_gap,zeros,zero_frequency\n");
+
+        for sample in &self.samples {
+            csv.push_str(&format!(
+                "{},{},{},{},{:.6}\n",
+                sample.turn, sample.distinct, sample.longest_gap, sample.zeros, sample.zeros as f64 / sample.turn as f64
+            ));
+        }
+
+        csv
+    }
+
+    /// One row per number below the limit; numbers never spoken have an empty turn.
+    pub fn first_spoken_csv(&self) -> String {
+        let mut csv = String::from("number,first_turn\n");
+
+        for (number, turn) in self.first_spoken.iter().enumerate() {
+            match turn {
+                Some(turn) => csv.push_str(&format!("{},{}\n", number, turn)),
+                None => csv.push_str(&format!("{},\n", number))
+            }
+        }
+
+        csv
+    }
+}
+
+#[test]
+fn test_analysis() {
+    // 0, 3, 6, 0, 3, 3, 1, 0, 4, 0
+    let analysis = Analysis::run(&[0, 3, 6], 10, 5, 8);
+
+    assert_eq!(analysis.current, Sample { turn: 10, distinct: 5, longest_gap: 4, zeros: 4 });
+    assert_eq!(analysis.samples, vec![
+        Sample { turn: 5, distinct: 3, longest_gap: 3, zeros: 2 },
+        Sample { turn: 10, distinct: 5, longest_gap: 4, zeros: 4 }
+    ]);
+    assert_eq!(analysis.zero_frequency(), 0.4);
+    assert_eq!(analysis.first_spoken, vec![Some(1), Some(7), None, Some(2), Some(9), None, Some(3), None]);
+}
+
+#[test]
+fn test_csv() {
+    let analysis = Analysis::run(&[0, 3, 6], 10, 5, 3);
+
+    assert_eq!(analysis.samples_csv(), "turn,distinct,longest_gap,zeros,zero_frequency\n5,3,3,2,0.400000\n10,5,4,4,0.400000\n");
+    assert_eq!(analysis.first_spoken_csv(), "number,first_turn\n0,1\n1,7\n2,\n");
+}
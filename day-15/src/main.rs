@@ -1,44 +1,286 @@
+mod analysis;
+
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+
+use analysis::Analysis;
+
+/// Numbers below this are tracked in a flat array. A number spoken on turn `n` is at most `n`, so
+/// this covers every number of a game up to about 33 million turns.
+const DENSE_LIMIT: usize = 1 << 25;
 
 #[derive(Debug)]
 struct MemoryGame {
     index: usize,
     last_number: usize,
-    last_indeces: HashMap<usize, usize>
+    dense_limit: usize,
+    /// The turn each small number was last spoken on, plus one so that zero means never.
+    dense: Vec<u32>,
+    /// The same for numbers too large for `dense`, or turns too late to fit in a `u32`.
+    sparse: HashMap<usize, usize>
 }
 
 impl MemoryGame {
     fn new(starters: &[usize]) -> Self {
-        let mut last_indeces = HashMap::new();
+        MemoryGame::with_dense_limit(starters, DENSE_LIMIT)
+    }
+
+    /// A game that keeps numbers from `dense_limit` up in a hash map; zero uses the map alone.
+    fn with_dense_limit(starters: &[usize], dense_limit: usize) -> Self {
+        let mut game = MemoryGame {
+            index: starters.len() - 1,
+            last_number: *starters.last().unwrap(),
+            dense_limit,
+            dense: Vec::new(),
+            sparse: HashMap::new()
+        };
+
+        game.reserve(starters.len().max(1 << 10));
         for (i, &n) in starters[..starters.len() - 1].iter().enumerate() {
-            last_indeces.insert(n, i);
+            game.remember(n, i);
+        }
+
+        game
+    }
+
+    /// Grows the dense array to fit every number a game of `turns` turns can speak.
+    fn reserve(&mut self, turns: usize) {
+        let length = turns.min(self.dense_limit);
+        if length <= self.dense.len() {
+            return;
+        }
+
+        self.dense.resize(length, 0);
+
+        let moved: Vec<usize> = self.sparse.keys().filter(|n| **n < length).copied().collect();
+        for n in moved {
+            let index = self.sparse.remove(&n).unwrap();
+            self.remember(n, index);
+        }
+    }
+
+    fn last_seen(&self, number: usize) -> Option<usize> {
+        match self.dense.get(number) {
+            Some(0) => self.sparse.get(&number).copied(),
+            Some(turn) => Some(*turn as usize - 1),
+            None => self.sparse.get(&number).copied()
         }
+    }
 
-        Self { index: starters.len() - 1, last_indeces, last_number: *starters.last().unwrap() }
+    fn remember(&mut self, number: usize, index: usize) {
+        if number < self.dense.len() && index < u32::MAX as usize {
+            self.dense[number] = index as u32 + 1;
+        } else {
+            self.sparse.insert(number, index);
+        }
     }
 
     fn generate(&mut self, size: usize) {
+        self.reserve(size);
+
         while self.index < size - 1 {
-            let next_number = match self.last_indeces.get(&self.last_number) {
-                Some(index) => self.index - index,
-                None => 0
-            };
+            self.next();
+        }
+    }
+
+    /// Every number spoken, starting with the starters.
+    fn spoken(starters: &[usize]) -> impl Iterator<Item = usize> + '_ {
+        starters.iter().copied().chain(MemoryGame::new(starters))
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        let mut seen: Vec<(usize, usize)> = self.dense.iter().enumerate()
+            .filter(|(_, turn)| **turn != 0)
+            .map(|(n, turn)| (n, *turn as usize - 1))
+            .chain(self.sparse.iter().map(|(n, index)| (*n, *index)))
+            .collect();
+        seen.sort_unstable();
 
-            self.last_indeces.insert(self.last_number, self.index);
-            self.index += 1;
-            self.last_number = next_number;
+        Checkpoint { index: self.index, last_number: self.last_number, seen }
+    }
+
+    fn resume(checkpoint: &Checkpoint) -> Self {
+        let mut game = MemoryGame {
+            index: checkpoint.index,
+            last_number: checkpoint.last_number,
+            dense_limit: DENSE_LIMIT,
+            dense: Vec::new(),
+            sparse: HashMap::new()
+        };
+
+        game.reserve((checkpoint.index + 1).max(1 << 10));
+        for (n, index) in &checkpoint.seen {
+            game.remember(*n, *index);
+        }
+
+        game
+    }
+}
+
+/// Yields the number spoken on each following turn, doubling `dense` whenever the turn outgrows
+/// it so that an open-ended game stays off the hash map.
+impl Iterator for MemoryGame {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.dense.len() && self.dense.len() < self.dense_limit {
+            self.reserve((self.dense.len() * 2).max(self.index + 1));
+        }
+
+        let next_number = match self.last_seen(self.last_number) {
+            Some(index) => self.index - index,
+            None => 0
+        };
+
+        self.remember(self.last_number, self.index);
+        self.index += 1;
+        self.last_number = next_number;
+
+        Some(next_number)
+    }
+}
+
+/// A game's state: the last turn played, the number spoken on it, and when every earlier number
+/// was last spoken.
+#[derive(Debug, PartialEq)]
+struct Checkpoint {
+    index: usize,
+    last_number: usize,
+    seen: Vec<(usize, usize)>
+}
+
+#[derive(Debug, PartialEq)]
+enum CheckpointError {
+    Empty,
+    InvalidLine(String)
+}
+
+impl fmt::Display for Checkpoint {
+    /// `<index> <last number>` followed by a `<number> <index>` line for each number seen.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", self.index, self.last_number)?;
+        for (n, index) in &self.seen {
+            writeln!(f, "{} {}", n, index)?;
         }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = CheckpointError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut pairs = text.lines().filter(|line| !line.is_empty()).map(|line| {
+            let invalid = || CheckpointError::InvalidLine(line.to_string());
+            let (a, b) = line.split_once(' ').ok_or_else(invalid)?;
+            Ok((a.parse().map_err(|_| invalid())?, b.parse().map_err(|_| invalid())?))
+        });
+
+        let (index, last_number) = pairs.next().ok_or(CheckpointError::Empty)??;
+        let seen = pairs.collect::<Result<Vec<(usize, usize)>, CheckpointError>>()?;
+
+        Ok(Checkpoint { index, last_number, seen })
     }
+}
 
+/// Times a game of `turns` turns with the dense array and with the hash map alone.
+fn benchmark(starters: &[usize], turns: usize) {
+    for (name, dense_limit) in &[("dense", DENSE_LIMIT), ("hash map", 0)] {
+        let start = Instant::now();
+        let mut game = MemoryGame::with_dense_limit(starters, *dense_limit);
+        game.generate(turns);
+
+        println!("{:>8}: {} after {} turns in {:.2?}", name, game.last_number, turns, start.elapsed());
+    }
 }
 
+const USAGE: &str = "usage: day-15 [--bench] [--save <checkpoint>] [--resume <checkpoint>]
+       day-15 --analyze <directory> [--turns <n>] [--interval <n>] [--limit <n>]";
+
+/// `--save` writes the game's state after part one; `--resume` plays part two on from a saved state.
+/// `--analyze` writes `samples.csv` with statistics every `interval` turns and `first.csv` with the
+/// turn each number below `limit` was first spoken.
 fn main() {
-    // Part one
-    let mut game = MemoryGame::new(&[17, 1, 3, 16, 19, 0]);
-    game.generate(2020);
-    println!("Part one: {}", game.last_number);
+    let mut args = std::env::args().skip(1);
+    let mut bench = false;
+    let (mut save, mut resume, mut analyze) = (None, None, None);
+    let (mut turns, mut interval, mut limit) = (30000000, 100000, 1000);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bench" => bench = true,
+            "--save" | "--resume" | "--analyze" => {
+                let path = match args.next() {
+                    Some(path) => path,
+                    None => return eprintln!("{} needs a path\n{}", arg, USAGE)
+                };
+
+                match arg.as_str() {
+                    "--save" => save = Some(path),
+                    "--resume" => resume = Some(path),
+                    _ => analyze = Some(path)
+                }
+            },
+            "--turns" | "--interval" | "--limit" => {
+                let n = match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => n,
+                    None => return eprintln!("{} needs a number\n{}", arg, USAGE)
+                };
+
+                match arg.as_str() {
+                    "--turns" => turns = n,
+                    "--interval" => interval = n,
+                    _ => limit = n
+                }
+            },
+            _ => return eprintln!("unknown argument {}\n{}", arg, USAGE)
+        }
+    }
+
+    let starters = [17, 1, 3, 16, 19, 0];
+
+    if bench {
+        return benchmark(&starters, 30000000);
+    }
+
+    if let Some(directory) = analyze {
+        let analysis = Analysis::run(&starters, turns, interval, limit);
+
+        let directory = std::path::Path::new(&directory);
+        std::fs::create_dir_all(directory).unwrap();
+        std::fs::write(directory.join("samples.csv"), analysis.samples_csv()).unwrap();
+        std::fs::write(directory.join("first.csv"), analysis.first_spoken_csv()).unwrap();
+
+        let summary = analysis.current;
+        return println!(
+            "{} turns: {} distinct numbers, longest gap {}, {} zeros ({:.4})",
+            summary.turn, summary.distinct, summary.longest_gap, summary.zeros, analysis.zero_frequency()
+        );
+    }
+
+    let mut game = match resume {
+        Some(path) => match std::fs::read_to_string(path).unwrap().parse() {
+            Ok(checkpoint) => MemoryGame::resume(&checkpoint),
+            Err(e) => return println!("Invalid checkpoint: {:?}", e)
+        },
+        None => {
+            // Part one
+            let mut game = MemoryGame::new(&starters);
+            game.generate(2020);
+            println!("Part one: {}", game.last_number);
+
+            if let Some(path) = save {
+                std::fs::write(path, game.checkpoint().to_string()).unwrap();
+            }
 
-    // Part one
+            game
+        }
+    };
+
+    // Part two
     game.generate(30000000);
     println!("Part two: {}", game.last_number);
 }
@@ -52,4 +294,51 @@ fn test_part_one() {
 
     game.generate(30000000);
     assert_eq!(game.last_number, 175594);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_spoken() {
+    let spoken: Vec<usize> = MemoryGame::spoken(&[0, 3, 6]).take(10).collect();
+    assert_eq!(spoken, vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0]);
+
+    assert_eq!(MemoryGame::spoken(&[1, 3, 2]).nth(2019), Some(1));
+    assert_eq!(MemoryGame::spoken(&[3, 1, 2]).nth(2019), Some(1836));
+}
+
+#[test]
+fn test_dense_growth() {
+    let mut game = MemoryGame::new(&[0, 3, 6]);
+    let mut hashed = MemoryGame::with_dense_limit(&[0, 3, 6], 0);
+    assert_eq!(game.nth(99999), hashed.nth(99999));
+
+    assert!(game.dense.len() > game.index);
+    assert!(game.sparse.is_empty());
+}
+
+#[test]
+fn test_hash_fallback() {
+    for dense_limit in &[0, 4, 100] {
+        let mut game = MemoryGame::with_dense_limit(&[2, 1, 3], *dense_limit);
+        game.generate(2020);
+        assert_eq!(game.last_number, 10);
+    }
+}
+
+#[test]
+fn test_checkpoint() {
+    let mut game = MemoryGame::new(&[0, 3, 6]);
+    game.generate(1000);
+
+    let text = game.checkpoint().to_string();
+    assert!(text.starts_with("999 "));
+
+    let checkpoint: Checkpoint = text.parse().unwrap();
+    assert_eq!(checkpoint, game.checkpoint());
+
+    let mut resumed = MemoryGame::resume(&checkpoint);
+    resumed.generate(2020);
+    assert_eq!(resumed.last_number, 436);
+
+    assert_eq!("".parse::<Checkpoint>(), Err(CheckpointError::Empty));
+    assert_eq!("3 0\n1-2".parse::<Checkpoint>(), Err(CheckpointError::InvalidLine("1-2".to_string())));
+}
@@ -1,37 +1,78 @@
-use std::collections::HashMap;
+mod matching;
+mod ticket;
 
-use regex::Regex;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use matching::{assign, Assignment, MatchingError};
+use ticket::Ticket;
+
+#[derive(Debug, PartialEq)]
+enum RuleError {
+    MissingSeparator(String),
+    EmptyName(String),
+    InvalidRange(String)
+}
+
+/// A field's name and the values it allows, as sorted intervals that neither overlap nor touch.
+#[derive(Debug, PartialEq)]
 struct TicketValidation {
     name: String,
-    lower_one: usize,
-    upper_one: usize,
-    lower_two: usize,
-    upper_two: usize
+    ranges: Vec<RangeInclusive<usize>>
+}
+
+/// Sorts ranges and merges any that overlap or are adjacent.
+fn merge_ranges(mut ranges: Vec<RangeInclusive<usize>>) -> Vec<RangeInclusive<usize>> {
+    ranges.sort_by_key(|range| *range.start());
+
+    let mut merged: Vec<RangeInclusive<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if *range.start() <= last.end() + 1 => {
+                *last = *last.start()..=(*last.end()).max(*range.end());
+            },
+            _ => merged.push(range)
+        }
+    }
+
+    merged
 }
 
-impl From<&str> for TicketValidation {
-    fn from(line: &str) -> Self {
-        let re = Regex::new(r"(\w+): (\d+)-(\d+) or (\d+)-(\d+)").unwrap();
-        let matches = re.captures(line).unwrap();
-
-        TicketValidation {
-            name: String::from(&matches[1]),
-            lower_one: matches[2].parse().unwrap(),
-            upper_one: matches[3].parse().unwrap(),
-            lower_two: matches[4].parse().unwrap(),
-            upper_two: matches[5].parse().unwrap()
+impl FromStr for TicketValidation {
+    type Err = RuleError;
+
+    /// `<name>: <low>-<high> or <low>-<high> ...`, where the name may contain spaces.
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (name, ranges) = line.split_once(": ").ok_or_else(|| RuleError::MissingSeparator(line.to_string()))?;
+        if name.trim().is_empty() {
+            return Err(RuleError::EmptyName(line.to_string()));
         }
+
+        let ranges = ranges.split(" or ")
+            .map(|range| {
+                let invalid = || RuleError::InvalidRange(range.to_string());
+                let (low, high) = range.trim().split_once('-').ok_or_else(invalid)?;
+                let (low, high): (usize, usize) = (low.parse().map_err(|_| invalid())?, high.parse().map_err(|_| invalid())?);
+
+                if low > high { Err(invalid()) } else { Ok(low..=high) }
+            })
+            .collect::<Result<Vec<RangeInclusive<usize>>, RuleError>>()?;
+
+        Ok(TicketValidation { name: name.to_string(), ranges: merge_ranges(ranges) })
     }
 }
 
 impl TicketValidation {
     fn validate(&self, other: usize) -> bool {
-        (other >= self.lower_one && other <= self.upper_one) ||
-        (other >= self.lower_two && other <= self.upper_two)
+        let index = self.ranges.partition_point(|range| *range.end() < other);
+        self.ranges.get(index).is_some_and(|range| range.contains(&other))
     }
 }
 
-fn parse_input(path: &str) -> (Vec<TicketValidation>, Vec<usize>, Vec<Vec<usize>>) {
+/// The rules, your ticket and the nearby tickets.
+type Notes = (Vec<TicketValidation>, Vec<usize>, Vec<Vec<usize>>);
+
+fn parse_input(path: &str) -> Result<Notes, RuleError> {
     let file = std::fs::read_to_string(path).unwrap();
     let mut lines = file.lines();
 
@@ -39,41 +80,46 @@ fn parse_input(path: &str) -> (Vec<TicketValidation>, Vec<usize>, Vec<Vec<usize>
     loop {
         let line = lines.next().unwrap();
         if line.is_empty() { break; }
-        ticket_validations.push(TicketValidation::from(line));
+        ticket_validations.push(line.parse()?);
     }
 
     lines.next();
-    let my_ticket = lines.next().unwrap().split(",").map(|n| n.parse().unwrap()).collect();
+    let my_ticket = lines.next().unwrap().split(',').map(|n| n.parse().unwrap()).collect();
 
     lines.next();
     lines.next();
     let mut nearby_tickets = Vec::new();
-    while let Some(line) = lines.next() {
+    for line in lines {
         nearby_tickets.push(
-            line.split(",").map(|n| n.parse().unwrap()).collect()
+            line.split(',').map(|n| n.parse().unwrap()).collect()
         );
     }
 
-    (ticket_validations, my_ticket, nearby_tickets)
+    Ok((ticket_validations, my_ticket, nearby_tickets))
 }
 
 fn find_invalid_fields(nearby: &[Vec<usize>], validations: &[TicketValidation]) -> Vec<usize> {
     nearby.iter()
         .flatten()
         .filter(|&&n| validations.iter().all(|val| !val.validate(n)))
-        .map(|n| *n)
+        .copied()
         .collect()
 }
 
-fn column_possibilities(nearby: &[Vec<usize>], validations: &[TicketValidation]) -> Vec<Vec<usize>> {
-    let valid_tickets: Vec<&Vec<usize>> = nearby
+/// The tickets where every value fits at least one rule.
+fn valid_tickets<'a>(nearby: &'a [Vec<usize>], validations: &[TicketValidation]) -> Vec<&'a Vec<usize>> {
+    nearby
         .iter()
         .filter(|ticket| {
             ticket.iter().all(|&n| {
                 validations.iter().any(|val| val.validate(n))
             })
         })
-        .collect();
+        .collect()
+}
+
+fn column_possibilities(nearby: &[Vec<usize>], validations: &[TicketValidation]) -> Vec<Vec<usize>> {
+    let valid_tickets = valid_tickets(nearby, validations);
 
     let col_count = valid_tickets[0].len();
 
@@ -94,94 +140,106 @@ fn column_possibilities(nearby: &[Vec<usize>], validations: &[TicketValidation])
         .collect()
 }
 
-fn only<F: Iterator<Item=usize>>(iter: &mut F) -> Option<usize> {
-    let first_number = match iter.next() {
-        Some(n) => n,
-        None => return None
-    };
-
-    match iter.next() {
-        Some(_) => None,
-        None => Some(first_number)
-    }
+fn solve_possibilities(columns: &[Vec<usize>], rule_count: usize) -> Result<Assignment, MatchingError> {
+    assign(columns, rule_count)
 }
 
-fn solve_possibilities(columns: &[Vec<usize>]) -> HashMap<usize, usize> {
-    // `possibilities` is a list of lists where each inner list is called a
-    // possibility_list and each entry in that is called a possibility
-    // solved contains a map of rule_index: column_index
-    let mut solved: HashMap<usize, usize> = HashMap::new();
+const USAGE: &str = "usage: day-16 [--csv <path>] [--field <name>]... [input]";
 
-    loop {
-        // Each iteration of this we are looking for possibility_lists that can
-        // only be one value.
-        let solved_possibility_lists: Vec<(usize, usize)> = columns
-            .iter()
-            .enumerate()
-            .filter(|(column_index, _)| solved.values().find(|&v| v == column_index).is_none())
-            .filter_map(|(column_index, rule_list)| {
-                let mut unclaimed_rule_indeces = rule_list
-                    .iter()
-                    .filter(|possibility| !solved.contains_key(possibility))
-                    .map(|&n| n);
-
-                only(&mut unclaimed_rule_indeces).map(|rule_index| (rule_index, column_index))
-            })
-            .collect();
-
-        if solved_possibility_lists.len() == 0 { break; }
-
-        solved_possibility_lists.iter().for_each(|(rule_index, column_index)|
-            { solved.insert(*rule_index, *column_index); });
+/// `--csv` writes every valid nearby ticket, decoded into named fields. `--field` prints one field
+/// of your own ticket, and may be given more than once.
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut csv_path = None;
+    let mut fields = Vec::new();
+    let mut input = String::from("input.txt");
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--csv" => match args.next() {
+                Some(path) => csv_path = Some(path),
+                None => return eprintln!("--csv needs a path\n{}", USAGE)
+            },
+            "--field" => match args.next() {
+                Some(name) => fields.push(name),
+                None => return eprintln!("--field needs a name\n{}", USAGE)
+            },
+            option if option.starts_with("--") => return eprintln!("unknown option {}\n{}", option, USAGE),
+            _ => input = arg
+        }
     }
 
-    solved
-}
-
-fn main() {
     // Part one
-    let (validations, my_ticket, nearby) = parse_input("input.txt");
+    let (validations, my_ticket, nearby) = parse_input(&input).unwrap();
     let invalid_sum = find_invalid_fields(&nearby, &validations).iter().sum::<usize>();
     println!("Part one: {}", invalid_sum);
 
-    // positions 0-5 are the departure fields
     let columns = column_possibilities(&nearby, &validations);
-    let mappings = solve_possibilities(&columns);
-    println!("{:?}", mappings);
-    let product = mappings.iter()
-        .filter(|(&rule_index, _)| rule_index < 6)
-        .map(|(_, &col_index)| my_ticket.get(col_index).unwrap())
-        .product::<usize>();
-
-    println!("Part two: {}", product);
+    let assignment = match solve_possibilities(&columns, validations.len()) {
+        Ok(assignment) => assignment,
+        Err(e) => return println!("Part two has no assignment: {:?}", e)
+    };
+
+    if !assignment.is_unique() {
+        println!("Warning: columns {:?} could be assigned differently", assignment.ambiguous);
+    }
+
+    let ticket = Ticket::decode(&my_ticket, &validations, &assignment);
+    println!("Part two: {}", ticket.product_of("departure"));
+
+    for name in &fields {
+        match ticket.get(name) {
+            Some(value) => println!("{}: {}", name, value),
+            None => println!("{}: no such field", name)
+        }
+    }
+
+    if let Some(path) = csv_path {
+        let decoded: Vec<Ticket> = valid_tickets(&nearby, &validations).into_iter()
+            .map(|values| Ticket::decode(values, &validations, &assignment))
+            .collect();
+
+        std::fs::write(path, ticket::to_csv(&decoded)).unwrap();
+    }
 }
 
 #[test]
 fn test_parsers() {
-    let val = TicketValidation::from("class: 1-3 or 5-7");
+    let val: TicketValidation = "class: 1-3 or 5-7".parse().unwrap();
     assert_eq!(val.name, String::from("class"));
-    assert_eq!(val.lower_one, 1);
-    assert_eq!(val.upper_one, 3);
-    assert_eq!(val.lower_two, 5);
-    assert_eq!(val.upper_two, 7);
-    assert_eq!(val.validate(2), true);
-    assert_eq!(val.validate(8), false);
+    assert_eq!(val.ranges, vec![1..=3, 5..=7]);
+    assert!(val.validate(2));
+    assert!(!val.validate(4));
+    assert!(!val.validate(8));
+
+    let val: TicketValidation = "departure location: 30-40 or 1-5 or 6-9 or 35-50".parse().unwrap();
+    assert_eq!(val.name, String::from("departure location"));
+    assert_eq!(val.ranges, vec![1..=9, 30..=50]);
+    assert!(val.validate(1) && val.validate(50) && !val.validate(10) && !val.validate(51));
+}
+
+#[test]
+fn test_rule_errors() {
+    assert_eq!("class 1-3".parse::<TicketValidation>(), Err(RuleError::MissingSeparator("class 1-3".to_string())));
+    assert_eq!(": 1-3".parse::<TicketValidation>(), Err(RuleError::EmptyName(": 1-3".to_string())));
+    assert_eq!("row: 1-3 or 9".parse::<TicketValidation>(), Err(RuleError::InvalidRange("9".to_string())));
+    assert_eq!("row: 5-3".parse::<TicketValidation>(), Err(RuleError::InvalidRange("5-3".to_string())));
 }
 
 #[test]
 fn test_part_one() {
-    let (validations, _, nearby) = parse_input("example1.txt");
+    let (validations, _, nearby) = parse_input("example1.txt").unwrap();
     assert_eq!(find_invalid_fields(&nearby, &validations).iter().sum::<usize>(), 71);
 }
 
 #[test]
 fn test_part_two() {
-    let (validations, _, nearby) = parse_input("example1.txt");
+    let (validations, _, nearby) = parse_input("example1.txt").unwrap();
     let columns = column_possibilities(&nearby, &validations);
-    let solved = solve_possibilities(&columns);
+    let solved = solve_possibilities(&columns, validations.len()).unwrap();
 
-    println!("{:?}", solved);
-    assert_eq!(solved[&0], 1);
-    assert_eq!(solved[&1], 0);
-    assert_eq!(solved[&2], 2);
+    assert_eq!(solved.column_of(0), Some(1));
+    assert_eq!(solved.column_of(1), Some(0));
+    assert_eq!(solved.column_of(2), Some(2));
+    assert!(solved.is_unique());
 }
\ No newline at end of file
@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+
+const UNMATCHED: usize = usize::MAX;
+
+/// Maximum bipartite matching between `left.len()` left vertices and `right_count` right vertices,
+/// where `left[l]` lists the right vertices `l` may pair with. Returns each left vertex's partner.
+pub fn hopcroft_karp(left: &[Vec<usize>], right_count: usize) -> Vec<Option<usize>> {
+    let mut pair_left = vec![UNMATCHED; left.len()];
+    let mut pair_right = vec![UNMATCHED; right_count];
+    let mut distance = vec![0; left.len()];
+
+    while layer(left, &pair_left, &pair_right, &mut distance) {
+        for l in 0..left.len() {
+            if pair_left[l] == UNMATCHED {
+                augment(l, left, &mut pair_left, &mut pair_right, &mut distance);
+            }
+        }
+    }
+
+    pair_left.into_iter().map(|r| if r == UNMATCHED { None } else { Some(r) }).collect()
+}
+
+/// Breadth first from the free left vertices, labelling each left vertex with its distance along
+/// alternating paths. Returns whether any path reaches a free right vertex.
+fn layer(left: &[Vec<usize>], pair_left: &[usize], pair_right: &[usize], distance: &mut [usize]) -> bool {
+    let mut queue = VecDeque::new();
+    for l in 0..left.len() {
+        if pair_left[l] == UNMATCHED {
+            distance[l] = 0;
+            queue.push_back(l);
+        } else {
+            distance[l] = UNMATCHED;
+        }
+    }
+
+    let mut found = false;
+    while let Some(l) = queue.pop_front() {
+        for &r in &left[l] {
+            match pair_right[r] {
+                UNMATCHED => found = true,
+                next if distance[next] == UNMATCHED => {
+                    distance[next] = distance[l] + 1;
+                    queue.push_back(next);
+                },
+                _ => ()
+            }
+        }
+    }
+
+    found
+}
+
+/// Depth first along the layers, flipping the path to a free right vertex if there is one.
+fn augment(l: usize, left: &[Vec<usize>], pair_left: &mut [usize], pair_right: &mut [usize], distance: &mut [usize]) -> bool {
+    for &r in &left[l] {
+        let next = pair_right[r];
+        if next == UNMATCHED || (distance[next] == distance[l] + 1 && augment(next, left, pair_left, pair_right, distance)) {
+            pair_left[l] = r;
+            pair_right[r] = l;
+            return true;
+        }
+    }
+
+    distance[l] = UNMATCHED;
+    false
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MatchingError {
+    /// No assignment gives every column a rule of its own; these columns were left without one.
+    Incomplete(Vec<usize>)
+}
+
+/// A rule for every column, and the columns that could have taken a different rule instead.
+#[derive(Debug, PartialEq)]
+pub struct Assignment {
+    pub rule_of_column: Vec<usize>,
+    pub ambiguous: Vec<usize>
+}
+
+impl Assignment {
+    pub fn is_unique(&self) -> bool {
+        self.ambiguous.is_empty()
+    }
+
+    pub fn column_of(&self, rule: usize) -> Option<usize> {
+        self.rule_of_column.iter().position(|r| *r == rule)
+    }
+}
+
+/// Assigns each column one of the rules it could be, with no rule used twice.
+pub fn assign(columns: &[Vec<usize>], rule_count: usize) -> Result<Assignment, MatchingError> {
+    let matching = hopcroft_karp(columns, rule_count);
+
+    let unmatched: Vec<usize> = matching.iter().enumerate().filter(|(_, r)| r.is_none()).map(|(c, _)| c).collect();
+    if !unmatched.is_empty() {
+        return Err(MatchingError::Incomplete(unmatched));
+    }
+
+    let rule_of_column: Vec<usize> = matching.into_iter().map(Option::unwrap).collect();
+    let ambiguous = (0..columns.len())
+        .filter(|column| has_alternative(columns, rule_count, &rule_of_column, *column))
+        .collect();
+
+    Ok(Assignment { rule_of_column, ambiguous })
+}
+
+/// Whether `column` can give up its rule and still leave every column with one: that is, whether
+/// an alternating path leads from it to a free rule, which may be its own rule reached through
+/// another column.
+fn has_alternative(columns: &[Vec<usize>], rule_count: usize, rule_of_column: &[usize], column: usize) -> bool {
+    let mut column_of_rule = vec![UNMATCHED; rule_count];
+    for (c, r) in rule_of_column.iter().enumerate() {
+        column_of_rule[*r] = c;
+    }
+
+    let own_rule = rule_of_column[column];
+    column_of_rule[own_rule] = UNMATCHED;
+
+    let mut visited = vec![false; rule_count];
+
+    let mut stack = vec![column];
+    while let Some(c) = stack.pop() {
+        for &r in &columns[c] {
+            if visited[r] || (c == column && r == own_rule) {
+                continue;
+            }
+            visited[r] = true;
+
+            match column_of_rule[r] {
+                UNMATCHED => return true,
+                next => stack.push(next)
+            }
+        }
+    }
+
+    false
+}
+
+#[test]
+fn test_hopcroft_karp() {
+    // Greedy would pair 0 with 0 and strand column 1
+    let matching = hopcroft_karp(&[vec![0, 1], vec![0], vec![1, 2]], 3);
+    assert_eq!(matching, vec![Some(1), Some(0), Some(2)]);
+
+    let matching = hopcroft_karp(&[vec![0], vec![0]], 2);
+    assert_eq!(matching.iter().filter(|r| r.is_some()).count(), 1);
+}
+
+#[test]
+fn test_assign() {
+    let assignment = assign(&[vec![0, 1], vec![0], vec![1, 2]], 3).unwrap();
+    assert_eq!(assignment.rule_of_column, vec![1, 0, 2]);
+    assert!(assignment.is_unique());
+    assert_eq!(assignment.column_of(2), Some(2));
+
+    // Columns 1 and 2 can swap rules, column 0 cannot move
+    let assignment = assign(&[vec![0], vec![1, 2], vec![1, 2]], 3).unwrap();
+    assert_eq!(assignment.ambiguous, vec![1, 2]);
+    assert!(!assignment.is_unique());
+
+    // A spare rule makes column 1 ambiguous
+    let assignment = assign(&[vec![0], vec![1, 2]], 3).unwrap();
+    assert_eq!(assignment.ambiguous, vec![1]);
+
+    assert_eq!(assign(&[vec![0], vec![0], vec![0, 1]], 2), Err(MatchingError::Incomplete(vec![1])));
+}
@@ -0,0 +1,85 @@
+use super::matching::Assignment;
+use super::TicketValidation;
+
+/// A ticket's values by field name, in the order the rules are listed.
+#[derive(Debug, PartialEq)]
+pub struct Ticket {
+    pub fields: Vec<(String, usize)>
+}
+
+impl Ticket {
+    /// Names each value by the rule assigned to its column.
+    pub fn decode(values: &[usize], validations: &[TicketValidation], assignment: &Assignment) -> Self {
+        let fields = validations.iter().enumerate()
+            .filter_map(|(rule, validation)| {
+                assignment.column_of(rule).map(|column| (validation.name.clone(), values[column]))
+            })
+            .collect();
+
+        Ticket { fields }
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.fields.iter().find(|(field, _)| field == name).map(|(_, value)| *value)
+    }
+
+    /// The product of every field whose name starts with `prefix`, or one if none do.
+    pub fn product_of(&self, prefix: &str) -> usize {
+        self.fields.iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .map(|(_, value)| *value)
+            .product()
+    }
+}
+
+/// Quotes a CSV cell if it holds a comma or a quote.
+fn escape(cell: &str) -> String {
+    if cell.contains(',') || cell.contains('"') {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// One row per ticket under a header of field names, taken from the first ticket.
+pub fn to_csv(tickets: &[Ticket]) -> String {
+    let header: Vec<String> = tickets.first()
+        .map(|ticket| ticket.fields.iter().map(|(name, _)| escape(name)).collect())
+        .unwrap_or_default();
+
+    let mut csv = format!("{}\n", header.join(","));
+    for ticket in tickets {
+        let values: Vec<String> = ticket.fields.iter().map(|(_, value)| value.to_string()).collect();
+        csv.push_str(&format!("{}\n", values.join(",")));
+    }
+
+    csv
+}
+
+#[test]
+fn test_decode() {
+    let (validations, my_ticket, nearby) = super::parse_input("example1.txt").unwrap();
+    let columns = super::column_possibilities(&nearby, &validations);
+    let assignment = super::solve_possibilities(&columns, validations.len()).unwrap();
+
+    let ticket = Ticket::decode(&my_ticket, &validations, &assignment);
+    assert_eq!(ticket.get("class"), Some(1));
+    assert_eq!(ticket.get("row"), Some(7));
+    assert_eq!(ticket.get("seat"), Some(14));
+    assert_eq!(ticket.get("zone"), None);
+
+    assert_eq!(ticket.product_of("s"), 14);
+    assert_eq!(ticket.product_of(""), 98);
+    assert_eq!(ticket.product_of("departure"), 1);
+}
+
+#[test]
+fn test_csv() {
+    let tickets = vec![
+        Ticket { fields: vec![("departure location".to_string(), 3), ("row, seat".to_string(), 9)] },
+        Ticket { fields: vec![("departure location".to_string(), 15), ("row, seat".to_string(), 1)] }
+    ];
+
+    assert_eq!(to_csv(&tickets), "departure location,\"row, seat\"\n3,9\n15,1\n");
+    assert_eq!(to_csv(&[]), "\n");
+}
@@ -1,164 +1,139 @@
-use std::num;
-
-#[derive(Debug)]
-struct Cube {
-    w: i32,
-    x: i32,
-    y: i32,
-    z: i32
-}
+use std::collections::{HashMap, HashSet};
+
+/// A cube's coordinates, starting with x and y from the input slice.
+type Cube<const D: usize> = [i32; D];
 
-struct Universe {
-    active_cubes: Vec<Cube>
+struct Universe<const D: usize> {
+    active_cubes: HashSet<Cube<D>>
 }
 
-impl Universe {
+impl<const D: usize> Universe<D> {
+    /// Reads the initial slice into the plane where every coordinate after x and y is zero.
     fn new(path: &str) -> Self {
-        let mut active_cubes = Vec::new();
+        assert!(D >= 2, "the initial slice needs at least two dimensions");
+
+        let mut active_cubes = HashSet::new();
 
         for (y, line) in std::fs::read_to_string(path).unwrap().lines().enumerate() {
             for (x, c) in line.chars().enumerate() {
-                if c == '#' { active_cubes.push(Cube { x: x as i32, y: y as i32, z: 0, w: 0 }); }
+                if c == '#' {
+                    let mut cube = [0; D];
+                    cube[0] = x as i32;
+                    cube[1] = y as i32;
+                    active_cubes.insert(cube);
+                }
             }
         }
 
         Universe { active_cubes }
     }
 
-    fn bounding_box(&self) -> BoundingBox {
-        let mut w_min = 10000;
-        let mut w_max = 0;
-        let mut x_min = 10000;
-        let mut x_max = 0;
-        let mut y_min = 10000;
-        let mut y_max = 0;
-        let mut z_min = 10000;
-        let mut z_max = 0;
-
-        for cube in self.active_cubes.iter() {
-            if cube.w < w_min { w_min = cube.w; }
-            if cube.w > w_max { w_max = cube.w; }
-            if cube.x < x_min { x_min = cube.x; }
-            if cube.x > x_max { x_max = cube.x; }
-            if cube.y < y_min { y_min = cube.y; }
-            if cube.y > y_max { y_max = cube.y; }
-            if cube.z < z_min { z_min = cube.z; }
-            if cube.z > z_max { z_max = cube.z; }
+    /// Every offset to a neighboring cube, 3^D - 1 of them.
+    fn neighbor_offsets() -> Vec<Cube<D>> {
+        let mut offsets = vec![[0; D]];
+
+        for axis in 0..D {
+            offsets = offsets.iter()
+                .flat_map(|offset| (-1..=1).map(move |delta| {
+                    let mut offset = *offset;
+                    offset[axis] = delta;
+                    offset
+                }))
+                .collect();
         }
 
-        BoundingBox { w_min, w_max, x_min, x_max, y_min, y_max, z_min, z_max }
+        offsets.retain(|offset| offset.iter().any(|delta| *delta != 0));
+        offsets
     }
 
-    fn active_cubes_adjacent_to(&self, cube: &Cube) -> usize {
-        self.active_cubes
-            .iter()
-            .filter(|c| {
-                i32::abs(c.w - cube.w) <= 1 &&
-                i32::abs(c.x - cube.x) <= 1 &&
-                i32::abs(c.y - cube.y) <= 1 &&
-                i32::abs(c.z - cube.z) <= 1 &&
-                !((cube.w == c.w) && (cube.y == c.y) && (cube.z == c.z) && (cube.x == c.x))
-            })
-            .count()
-    }
-
-    fn step_3d(&mut self) {
-        let mut next_generation = Vec::new();
-        let bounding_box = self.bounding_box();
-
-        for x in (bounding_box.x_min - 1)..=(bounding_box.x_max + 1) {
-            for y in (bounding_box.y_min - 1)..=(bounding_box.y_max + 1) {
-                for z in (bounding_box.z_min- 1)..=(bounding_box.z_max + 1) {
-                    let cube = Cube { w: 0, x, y, z };
-                    let nearby_active = self.active_cubes_adjacent_to(&cube);
-
-                    if nearby_active < 2 || nearby_active > 3 { continue; }
-
-                    let is_active = self.active_cubes
-                        .iter()
-                        .find(|c| c.x == x && c.y == y && c.z == z)
-                        .is_some();
-
-                    let next_active_state = match (is_active, nearby_active) {
-                        (true, 2..=3) => true,
-                        (false, 3) => true,
-                        _ => false
-                    };
-
-                    if next_active_state { next_generation.push(cube); }
+    /// Each active cube adds one to the count of every cube around it, so only cubes next to an
+    /// active one are ever considered.
+    fn step(&mut self) {
+        let offsets = Self::neighbor_offsets();
+        let mut counts: HashMap<Cube<D>, usize> = HashMap::with_capacity(self.active_cubes.len() * offsets.len());
+
+        for cube in &self.active_cubes {
+            for offset in &offsets {
+                let mut neighbor = *cube;
+                for axis in 0..D {
+                    neighbor[axis] += offset[axis];
                 }
+
+                *counts.entry(neighbor).or_insert(0) += 1;
             }
         }
 
-        self.active_cubes = next_generation;
+        self.active_cubes = counts.into_iter()
+            .filter(|(cube, count)| *count == 3 || (*count == 2 && self.active_cubes.contains(cube)))
+            .map(|(cube, _)| cube)
+            .collect();
     }
+}
 
-    fn step_4d(&mut self) {
-        let mut next_generation = Vec::new();
-        let bounding_box = self.bounding_box();
-
-        for w in (bounding_box.w_min - 1)..=(bounding_box.w_max + 1) {
-            for x in (bounding_box.x_min - 1)..=(bounding_box.x_max + 1) {
-                for y in (bounding_box.y_min - 1)..=(bounding_box.y_max + 1) {
-                    for z in (bounding_box.z_min- 1)..=(bounding_box.z_max + 1) {
-                        let cube = Cube { w, x, y, z };
-                        let nearby_active = self.active_cubes_adjacent_to(&cube);
-
-                        if nearby_active < 2 || nearby_active > 3 { continue; }
-
-                        let is_active = self.active_cubes
-                            .iter()
-                            .find(|c| c.w == w && c.x == x && c.y == y && c.z == z)
-                            .is_some();
+/// The number of active cubes after `cycles` cycles in `D` dimensions.
+fn simulate<const D: usize>(path: &str, cycles: usize) -> usize {
+    let mut universe = Universe::<D>::new(path);
+    (0..cycles).for_each(|_| universe.step());
+    universe.active_cubes.len()
+}
 
-                        let next_active_state = match (is_active, nearby_active) {
-                            (true, 2..=3) => true,
-                            (false, 3) => true,
-                            _ => false
-                        };
+const USAGE: &str = "usage: day-17 [--dimensions <2-6>] [input]";
 
-                        if next_active_state { next_generation.push(cube); }
-                    }
-                }
-            }
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut dimensions = None;
+    let mut input = String::from("input.txt");
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dimensions" => match args.next().and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) => dimensions = Some(n),
+                None => return eprintln!("--dimensions needs a number\n{}", USAGE)
+            },
+            option if option.starts_with("--") => return eprintln!("unknown option {}\n{}", option, USAGE),
+            _ => input = arg
         }
+    }
 
-        self.active_cubes = next_generation;
+    if let Some(dimensions) = dimensions {
+        let active = match dimensions {
+            2 => simulate::<2>(&input, 6),
+            3 => simulate::<3>(&input, 6),
+            4 => simulate::<4>(&input, 6),
+            5 => simulate::<5>(&input, 6),
+            6 => simulate::<6>(&input, 6),
+            other => return println!("Unsupported number of dimensions: {}", other)
+        };
+
+        return println!("{} dimensions: {}", dimensions, active);
     }
-}
 
-struct BoundingBox {
-    w_min: i32,
-    w_max: i32,
-    x_min: i32,
-    x_max: i32,
-    y_min: i32,
-    y_max: i32,
-    z_min: i32,
-    z_max: i32
+    println!("Part one: {}", simulate::<3>(&input, 6));
+    println!("Part two: {}", simulate::<4>(&input, 6));
 }
 
-fn main() {
-    let mut universe = Universe::new("input.txt");
-    (0..6).for_each(|_| universe.step_3d());
-    println!("Part one: {}", universe.active_cubes.len());
 
-    let mut universe = Universe::new("input.txt");
-    (0..6).for_each(|_| universe.step_4d());
-    println!("Part two: {}", universe.active_cubes.len());
+#[test]
+fn test_part_one() {
+    assert_eq!(simulate::<3>("example1.txt", 6), 112);
 }
 
+#[test]
+fn test_part_two() {
+    assert_eq!(simulate::<4>("example1.txt", 6), 848);
+}
 
 #[test]
-fn test_part_one() {
-    let mut universe = Universe::new("example1.txt");
-    (0..6).for_each(|_| universe.step_3d());
-    assert_eq!(universe.active_cubes.len(), 112);
+fn test_other_dimensions() {
+    // In two dimensions the example is a glider, which never grows
+    assert_eq!(simulate::<2>("example1.txt", 6), 5);
+    assert_eq!(simulate::<5>("example1.txt", 3), 2288);
+    assert_eq!(simulate::<6>("example1.txt", 2), 464);
 }
 
 #[test]
-fn test_part_two() {
-    let mut universe = Universe::new("example1.txt");
-    (0..6).for_each(|_| universe.step_4d());
-    assert_eq!(universe.active_cubes.len(), 848);
-}
\ No newline at end of file
+fn test_neighbor_offsets() {
+    assert_eq!(Universe::<2>::neighbor_offsets().len(), 8);
+    assert_eq!(Universe::<3>::neighbor_offsets().len(), 26);
+    assert_eq!(Universe::<6>::neighbor_offsets().len(), 728);
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use super::vm::{DecodeError, Instruction, InstructionSet};
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum AssemblyError {
+    DuplicateLabel { line: usize, label: String },
+    ReservedLabel { line: usize, label: String },
+    UnknownLabel { line: usize, label: String },
+    Decode { line: usize, error: DecodeError }
+}
+
+#[derive(Debug)]
+pub struct Assembly {
+    pub instructions: Vec<Instruction>,
+    pub labels: HashMap<String, usize>,
+    /// The 1-based source line that produced each instruction.
+    pub source_map: Vec<usize>
+}
+
+impl Assembly {
+    /// The program in the plain `acc/jmp/nop` text format the VM reads.
+    pub fn text(&self) -> String {
+        self.instructions.iter()
+            .map(|i| format!("{}\n", i))
+            .collect()
+    }
+
+    pub fn source_line(&self, pc: usize) -> Option<usize> {
+        self.source_map.get(pc).copied()
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find(['#', ';']) {
+        Some(index) => &line[..index],
+        None => line
+    }
+}
+
+fn is_identifier(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_register(token: &str) -> bool {
+    matches!(token, "a" | "b" | "c" | "d")
+}
+
+/// Assembles source with labels (`loop: acc +1`, `jmp loop`) and `#`/`;` comments.
+pub fn assemble(source: &str, set: &InstructionSet) -> Result<Assembly, AssemblyError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+
+    for (index, raw) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut rest = strip_comment(raw).trim();
+
+        while let Some(colon) = rest.find(':') {
+            let label = rest[..colon].trim();
+            if !is_identifier(label) { break; }
+
+            if is_register(label) {
+                return Err(AssemblyError::ReservedLabel { line, label: label.to_string() });
+            }
+
+            if labels.insert(label.to_string(), statements.len()).is_some() {
+                return Err(AssemblyError::DuplicateLabel { line, label: label.to_string() });
+            }
+
+            rest = rest[colon + 1..].trim();
+        }
+
+        if !rest.is_empty() {
+            statements.push((line, rest));
+        }
+    }
+
+    let mut instructions = Vec::new();
+    let mut source_map = Vec::new();
+
+    for (pc, (line, statement)) in statements.into_iter().enumerate() {
+        let mut tokens: Vec<String> = statement.split_whitespace().map(|t| t.to_string()).collect();
+
+        if tokens.len() > 1 {
+            let last = tokens.last_mut().unwrap();
+            if is_identifier(last) && !is_register(last) {
+                let target = labels.get(last.as_str())
+                    .ok_or_else(|| AssemblyError::UnknownLabel { line, label: last.clone() })?;
+
+                *last = format!("{:+}", *target as i64 - pc as i64);
+            }
+        }
+
+        let instruction = set.decode(&tokens.join(" "))
+            .map_err(|error| AssemblyError::Decode { line, error })?;
+
+        instructions.push(instruction);
+        source_map.push(line);
+    }
+
+    Ok(Assembly { instructions, labels, source_map })
+}
+
+fn jump_target(pc: usize, instruction: Instruction) -> Option<i64> {
+    match instruction {
+        Instruction::Jmp(x) | Instruction::Jz(_, x) => Some(pc as i64 + x as i64),
+        _ => None
+    }
+}
+
+/// Renders instructions as assembly, naming every in-range jump target with a label.
+pub fn disassemble(instructions: &[Instruction]) -> String {
+    let len = instructions.len() as i64;
+    let mut targets: Vec<usize> = instructions.iter()
+        .enumerate()
+        .filter_map(|(pc, i)| jump_target(pc, *i))
+        .filter(|target| (0..=len).contains(target))
+        .map(|target| target as usize)
+        .collect();
+
+    targets.sort_unstable();
+    targets.dedup();
+
+    let names: HashMap<usize, String> = targets.iter()
+        .enumerate()
+        .map(|(n, &target)| (target, if target == instructions.len() { "end".to_string() } else { format!("l{}", n) }))
+        .collect();
+
+    let mut output = String::new();
+    for (pc, instruction) in instructions.iter().enumerate() {
+        if let Some(name) = names.get(&pc) {
+            output.push_str(&format!("{}:\n", name));
+        }
+
+        let target = jump_target(pc, *instruction)
+            .filter(|t| *t >= 0)
+            .and_then(|t| names.get(&(t as usize)));
+        let line = match (instruction, target) {
+            (Instruction::Jmp(_), Some(name)) => format!("jmp {}", name),
+            (Instruction::Jz(register, _), Some(name)) => format!("jz {} {}", register, name),
+            _ => instruction.to_string()
+        };
+
+        output.push_str(&format!("    {}\n", line));
+    }
+
+    if let Some(name) = names.get(&instructions.len()) {
+        output.push_str(&format!("{}:\n", name));
+    }
+
+    output
+}
+
+#[test]
+fn test_assemble_labels() {
+    let source = "\
+# count down b from three
+    acc b +3
+top: jz b done   ; leave once b hits zero
+    acc +2
+    acc b -1
+    jmp top
+done:
+    out";
+
+    let assembly = assemble(source, &InstructionSet::extended()).unwrap();
+
+    assert_eq!(assembly.text(), "acc b +3\njz b +4\nacc +2\nacc b -1\njmp -3\nout\n");
+    assert_eq!(assembly.labels["top"], 1);
+    assert_eq!(assembly.labels["done"], 5);
+    assert_eq!(assembly.source_line(0), Some(2));
+    assert_eq!(assembly.source_line(5), Some(8));
+}
+
+#[test]
+fn test_assembly_errors() {
+    let set = InstructionSet::extended();
+
+    assert_eq!(assemble("jmp nowhere", &set).err(), Some(AssemblyError::UnknownLabel { line: 1, label: "nowhere".to_string() }));
+    assert_eq!(assemble("x: nop +0\nx: nop +0", &set).err(), Some(AssemblyError::DuplicateLabel { line: 2, label: "x".to_string() }));
+    assert_eq!(assemble("b: nop +0", &set).err(), Some(AssemblyError::ReservedLabel { line: 1, label: "b".to_string() }));
+    assert_eq!(assemble("\n\nfoo +1", &set).err(), Some(AssemblyError::Decode { line: 3, error: DecodeError::UnknownOpcode("foo".to_string()) }));
+}
+
+#[test]
+fn test_disassemble_round_trip() {
+    let program = super::vm::Program::new("example1.txt");
+    let listing = disassemble(&program.instructions);
+
+    assert_eq!(listing.lines().next(), Some("    nop +0"));
+    assert!(listing.contains("l1:\n    acc +3\n"));
+    assert!(listing.contains("    jmp l1\n"));
+
+    let assembly = assemble(&listing, &InstructionSet::extended()).unwrap();
+    assert_eq!(assembly.instructions, program.instructions);
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use super::vm::{Instruction, Program, StepResult};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Breakpoint {
+    Pc(usize),
+    Acc(i32)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TraceEntry {
+    pub pc: usize,
+    pub instruction: Instruction,
+    pub acc: i32
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct LoopReport {
+    pub entry: usize,
+    pub acc: i32,
+    pub cycle: Vec<(usize, Instruction)>
+}
+
+impl fmt::Display for LoopReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Loop of {} instructions entered at pc {} (acc = {}):", self.cycle.len(), self.entry, self.acc)?;
+        for (pc, instruction) in &self.cycle {
+            writeln!(f, "  {:>5}: {}", pc, instruction)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Stop {
+    Breakpoint(Breakpoint),
+    Loop(LoopReport),
+    Terminated(StepResult)
+}
+
+pub struct Debugger {
+    pub program: Program,
+    pub breakpoints: Vec<Breakpoint>,
+    pub trace: Vec<TraceEntry>
+}
+
+impl Debugger {
+    pub fn new(mut program: Program) -> Self {
+        program.reset();
+        Debugger { program, breakpoints: Vec::new(), trace: Vec::new() }
+    }
+
+    pub fn restart(&mut self) {
+        self.program.reset();
+        self.trace.clear();
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.retain(|b| *b != breakpoint);
+    }
+
+    /// Executes a single instruction, recording it in the trace.
+    pub fn step(&mut self) -> StepResult {
+        let pc = self.program.pc;
+        let instruction = match self.program.current_instruction() {
+            Some(instruction) => instruction,
+            None => return StepResult::EndOfProgram
+        };
+
+        let result = self.program.step();
+        if let StepResult::AlreadyVisited(_) = result {
+            return result;
+        }
+
+        self.trace.push(TraceEntry { pc, instruction, acc: self.program.acc() });
+        result
+    }
+
+    /// Runs until a breakpoint is hit, a loop is detected or the program terminates.
+    /// At least one instruction is always executed so that a breakpoint can be resumed from.
+    pub fn resume(&mut self) -> Stop {
+        loop {
+            let acc_before = self.program.acc();
+            match self.step() {
+                StepResult::Ok { .. } | StepResult::Output(_) => (),
+                StepResult::AlreadyVisited(pc) => return Stop::Loop(self.loop_report(pc)),
+                result => return Stop::Terminated(result)
+            }
+
+            if let Some(breakpoint) = self.hit_breakpoint(acc_before) {
+                return Stop::Breakpoint(breakpoint);
+            }
+        }
+    }
+
+    /// Runs ignoring breakpoints, returning the loop if one is found.
+    pub fn run_until_loop(&mut self) -> Option<LoopReport> {
+        loop {
+            match self.step() {
+                StepResult::Ok { .. } | StepResult::Output(_) => (),
+                StepResult::AlreadyVisited(pc) => return Some(self.loop_report(pc)),
+                _ => return None
+            }
+        }
+    }
+
+    /// Accumulator breakpoints only fire when the accumulator changes to the watched value.
+    fn hit_breakpoint(&self, acc_before: i32) -> Option<Breakpoint> {
+        let acc = self.program.acc();
+
+        self.breakpoints.iter()
+            .find(|breakpoint| match breakpoint {
+                Breakpoint::Pc(pc) => *pc == self.program.pc,
+                Breakpoint::Acc(watched) => *watched == acc && acc != acc_before
+            })
+            .copied()
+    }
+
+    fn loop_report(&self, entry: usize) -> LoopReport {
+        let start = self.trace.iter().rposition(|e| e.pc == entry).unwrap_or(0);
+        let cycle = self.trace[start..].iter().map(|e| (e.pc, e.instruction)).collect();
+
+        LoopReport { entry, acc: self.program.acc(), cycle }
+    }
+
+    pub fn write_trace(&self, path: &str) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "pc\tinstruction\tacc")?;
+
+        for entry in &self.trace {
+            writeln!(writer, "{}\t{}\t{}", entry.pc, entry.instruction, entry.acc)?;
+        }
+
+        writer.flush()
+    }
+}
+
+#[test]
+fn test_breakpoints() {
+    let mut debugger = Debugger::new(Program::new("example1.txt"));
+    debugger.add_breakpoint(Breakpoint::Pc(6));
+    debugger.add_breakpoint(Breakpoint::Acc(5));
+
+    assert_eq!(debugger.resume(), Stop::Breakpoint(Breakpoint::Pc(6)));
+    assert_eq!(debugger.program.acc(), 1);
+    assert_eq!(debugger.resume(), Stop::Breakpoint(Breakpoint::Acc(5)));
+    assert_eq!(debugger.program.pc, 4);
+
+    debugger.remove_breakpoint(Breakpoint::Acc(5));
+    assert!(matches!(debugger.resume(), Stop::Loop(_)));
+}
+
+#[test]
+fn test_loop_report() {
+    let mut debugger = Debugger::new(Program::new("example1.txt"));
+    let report = debugger.run_until_loop().unwrap();
+
+    assert_eq!(report.entry, 1);
+    assert_eq!(report.acc, 5);
+    assert_eq!(report.cycle.iter().map(|(pc, _)| *pc).collect::<Vec<usize>>(), vec![1, 2, 6, 7, 3, 4]);
+    assert_eq!(debugger.trace.len(), 7);
+}
@@ -1,124 +1,140 @@
-#[derive(Eq, PartialEq, Debug)]
-enum Instruction {
-    Jmp(i32),
-    Acc(i32),
-    Nop(i32)
-}
-
-impl From<&str> for Instruction {
-    fn from(line: &str) -> Self {
-        let operand = line[4..].parse().unwrap();
-
-        match &line[0..3] {
-            "acc" => Instruction::Acc(operand),
-            "jmp" => Instruction::Jmp(operand),
-            _ => Instruction::Nop(operand)
-        }
+mod assembler;
+mod debugger;
+mod repair;
+mod vm;
+
+use std::io::{self, BufRead, Write};
+
+use debugger::{Breakpoint, Debugger, Stop};
+use repair::Repair;
+use vm::{InstructionSet, Program, StepResult};
+#[cfg(test)]
+use vm::{Instruction, Register};
+
+fn parse_breakpoint(kind: &str, value: &str) -> Option<Breakpoint> {
+    match kind {
+        "pc" => value.parse().ok().map(Breakpoint::Pc),
+        "acc" => value.parse().ok().map(Breakpoint::Acc),
+        _ => None
     }
 }
 
-struct Program {
-    instructions: Vec<Instruction>,
-    executed: Vec<usize>,
-    acc: i32,
-    pc: usize
-}
-
-#[derive(Debug, Eq, PartialEq)]
-enum StepResult {
-    Ok,
-    EndOfProgram,
-    AlreadyVisited,
-    OutOfBounds
-}
-
-impl Program {
-    fn new(path: &str) -> Self {
-        let instructions = std::fs::read_to_string(path)
-            .unwrap()
-            .lines()
-            .map(|l| Instruction::from(l))
-            .collect();
-
-        Program { instructions, executed: Vec::new(), acc: 0, pc: 0 }
-    }
-
-    fn reset(&mut self) {
-        self.executed = Vec::new();
-        self.acc = 0;
-        self.pc = 0;
-    }
-
-    fn run(&mut self) -> Result<(), StepResult> {
-        self.reset();
-
-        loop {
-            match self.step() {
-                StepResult::Ok => (),
-                StepResult::EndOfProgram => return Ok(()),
-                StepResult::AlreadyVisited => return Err(StepResult::AlreadyVisited),
-                StepResult::OutOfBounds => return Err(StepResult::OutOfBounds)
-            }
-        }
-    }
-
-    fn step(&mut self) -> StepResult {
-        if self.executed.contains(&self.pc) {
-            return StepResult::AlreadyVisited;
-        }
-
-        self.executed.push(self.pc);
-        self.pc = match self.instructions[self.pc] {
-            Instruction::Nop(_) => self.pc + 1,
-            Instruction::Acc(x) => { self.acc += x; self.pc + 1 },
-            Instruction::Jmp(x) => ((self.pc as i32) + x) as usize
-        };
-
-        match self.pc {
-            x if x > self.instructions.len() => StepResult::OutOfBounds,
-            x if x == self.instructions.len() => StepResult::EndOfProgram,
-            _ => StepResult::Ok
-        }
+fn print_location(debugger: &Debugger) {
+    match debugger.program.current_instruction() {
+        Some(instruction) => println!("pc = {}, acc = {}, next: {}", debugger.program.pc, debugger.program.acc(), instruction),
+        None => println!("pc = {}, acc = {}", debugger.program.pc, debugger.program.acc())
     }
+}
 
-    fn attempt_correction(&mut self) -> bool {
-        for index in 0..self.instructions.len() {
-            let (original_instruction, mutated_instruction) = match self.instructions[index] {
-                Instruction::Jmp(x) => (Instruction::Jmp(x), Instruction::Nop(x)),
-                Instruction::Nop(x) => (Instruction::Nop(x), Instruction::Jmp(x)), 
-                Instruction::Acc(_) => continue
-            };
-    
-            // Perform the swap
-            self.instructions[index] = mutated_instruction;
-    
-            // Run the program and return true if a good swap was found
-            if self.run().is_ok() { return true; }
-
-            // Otherwise revert the swap and continue
-            self.instructions[index] = original_instruction;
+fn debug(path: &str) {
+    let mut debugger = Debugger::new(Program::new(path));
+    println!("Commands: step [n], continue, loop, break pc|acc <n>, delete pc|acc <n>, regs, trace <file>, restart, quit");
+    print_location(&debugger);
+
+    let stdin = io::stdin();
+    loop {
+        print!("(debug) ");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 { break; }
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            ["step"] | ["s"] => { println!("{:?}", debugger.step()); print_location(&debugger) },
+            ["step", n] | ["s", n] => {
+                for _ in 0..n.parse().unwrap_or(1) {
+                    match debugger.step() {
+                        StepResult::Ok { .. } | StepResult::Output(_) => (),
+                        result => { println!("{:?}", result); break; }
+                    }
+                }
+                print_location(&debugger)
+            },
+            ["continue"] | ["c"] => {
+                match debugger.resume() {
+                    Stop::Loop(report) => print!("{}", report),
+                    stop => println!("{:?}", stop)
+                }
+                print_location(&debugger)
+            },
+            ["loop"] => match debugger.run_until_loop() {
+                Some(report) => print!("{}", report),
+                None => println!("Program terminated without looping")
+            },
+            ["break", kind, value] | ["b", kind, value] => match parse_breakpoint(kind, value) {
+                Some(breakpoint) => debugger.add_breakpoint(breakpoint),
+                None => println!("Unknown breakpoint")
+            },
+            ["delete", kind, value] => match parse_breakpoint(kind, value) {
+                Some(breakpoint) => debugger.remove_breakpoint(breakpoint),
+                None => println!("Unknown breakpoint")
+            },
+            ["regs"] => println!("{:?}", debugger.program.registers),
+            ["trace", path] => match debugger.write_trace(path) {
+                Ok(()) => println!("Wrote {} entries to {}", debugger.trace.len(), path),
+                Err(e) => println!("Could not write trace: {}", e)
+            },
+            ["restart"] => { debugger.restart(); print_location(&debugger) },
+            ["quit"] | ["q"] => break,
+            [] => (),
+            _ => println!("Unknown command")
         }
-
-        false
     }
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let path = args.get(2).map(|s| s.as_str()).unwrap_or("input.txt");
+    match args.get(1).map(|s| s.as_str()) {
+        Some("debug") => return debug(path),
+        Some("assemble") => {
+            let source = std::fs::read_to_string(path).unwrap();
+            match assembler::assemble(&source, &InstructionSet::extended()) {
+                Ok(assembly) => {
+                    // The label table goes to stderr so stdout stays a loadable program
+                    let mut labels: Vec<(&String, &usize)> = assembly.labels.iter().collect();
+                    labels.sort_by_key(|(_, pc)| **pc);
+                    for (label, pc) in labels {
+                        match assembly.source_line(*pc) {
+                            Some(line) => eprintln!("{} = {} (line {})", label, pc, line),
+                            None => eprintln!("{} = {} (end of program)", label, pc)
+                        }
+                    }
+
+                    print!("{}", assembly.text())
+                },
+                Err(e) => eprintln!("{:?}", e)
+            }
+            return;
+        },
+        Some("disassemble") => return print!("{}", assembler::disassemble(&Program::new(path).instructions)),
+        _ => ()
+    }
+
     // Part one
     let mut program = Program::new("input.txt");
     let _result = program.run();
-    println!("Part 1: {}", program.acc);
+    println!("Part 1: {}", program.acc());
 
     // Part two
-    assert!(program.attempt_correction());
-    println!("Part 2: {}", program.acc);
+    match repair::analyze(&program) {
+        Repair::Unique(index) => {
+            program.flip(index);
+            assert!(program.run().is_ok());
+            println!("Part 2: {} (flipped instruction {})", program.acc(), index);
+        },
+        other => println!("Part 2: no unique repair ({:?})", other)
+    }
 }
 
 #[test]
 fn test_instruction_parser() {
-    assert_eq!(Instruction::from("jmp +0"), Instruction::Jmp(0));
-    assert_eq!(Instruction::from("acc +1"), Instruction::Acc(1));
-    assert_eq!(Instruction::from("nop -40"), Instruction::Nop(-40));
+    let set = InstructionSet::handheld();
+
+    assert_eq!(set.decode("jmp +0"), Ok(Instruction::Jmp(0)));
+    assert_eq!(set.decode("acc +1"), Ok(Instruction::Acc(Register::A, 1)));
+    assert_eq!(set.decode("nop -40"), Ok(Instruction::Nop(-40)));
 }
 
 #[test]
@@ -126,8 +142,8 @@ fn test_example_1() {
     let mut program = Program::new("example1.txt");
     let result = program.run();
 
-    assert_eq!(result.err().unwrap(), StepResult::AlreadyVisited);
-    assert_eq!(program.acc, 5);
+    assert_eq!(result.err().unwrap(), StepResult::AlreadyVisited(1));
+    assert_eq!(program.acc(), 5);
 }
 
 #[test]
@@ -135,6 +151,6 @@ fn test_example_2() {
     let mut program = Program::new("example1.txt");
     let corrected = program.attempt_correction();
 
-    assert_eq!(corrected, true);
-    assert_eq!(program.acc, 8);
+    assert!(corrected);
+    assert_eq!(program.acc(), 8);
 }
@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+
+use super::vm::{Instruction, Program, StepResult};
+#[cfg(test)]
+use super::vm::InstructionSet;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Repair {
+    NotNeeded,
+    Unique(usize),
+    Ambiguous(Vec<usize>),
+    Impossible
+}
+
+/// Possible next pcs of an instruction; `jz` may go either way, so for programs using it this is
+/// only an over-approximation of where execution can go.
+fn successors(pc: usize, instruction: Instruction) -> [Option<i64>; 2] {
+    let next = Some(pc as i64 + 1);
+
+    match instruction {
+        Instruction::Jmp(x) => [Some(pc as i64 + x as i64), None],
+        Instruction::Jz(_, x) => [next, Some(pc as i64 + x as i64)],
+        Instruction::Hlt => [None, None],
+        _ => [next, None]
+    }
+}
+
+/// Marks every pc from which execution can reach the end of the program or a `hlt`.
+/// The extra final entry stands for the end of the program itself.
+pub fn terminating(instructions: &[Instruction]) -> Vec<bool> {
+    let len = instructions.len();
+    let mut predecessors = vec![Vec::new(); len + 1];
+    let mut reaches = vec![false; len + 1];
+    let mut queue = VecDeque::new();
+
+    for (pc, instruction) in instructions.iter().enumerate() {
+        if *instruction == Instruction::Hlt {
+            reaches[pc] = true;
+            queue.push_back(pc);
+        }
+
+        for target in successors(pc, *instruction).iter().flatten() {
+            if (0..=len as i64).contains(target) {
+                predecessors[*target as usize].push(pc);
+            }
+        }
+    }
+
+    reaches[len] = true;
+    queue.push_back(len);
+
+    while let Some(pc) = queue.pop_front() {
+        for &predecessor in &predecessors[pc] {
+            if !reaches[predecessor] {
+                reaches[predecessor] = true;
+                queue.push_back(predecessor);
+            }
+        }
+    }
+
+    reaches
+}
+
+/// Finds the `jmp`/`nop` swaps that make the program terminate in a single pass.
+///
+/// Without `jz`, only instructions on the original execution path matter, and a swap works exactly
+/// when the swapped instruction's target can reach termination in the unmodified control-flow
+/// graph. Whether a `jz` branches depends on register values the graph cannot see, so programs
+/// using it are checked by running every swap instead.
+pub fn analyze(program: &Program) -> Repair {
+    if program.instructions.iter().any(|instruction| matches!(instruction, Instruction::Jz(..))) {
+        return simulate(program);
+    }
+
+    let reaches = terminating(&program.instructions);
+    let mut walker = Program::from_instructions(program.instructions.clone());
+    let mut repairs = Vec::new();
+
+    loop {
+        let pc = walker.pc;
+        let flipped = walker.current_instruction().and_then(|instruction| instruction.flipped());
+
+        match walker.step() {
+            StepResult::Ok { .. } | StepResult::Output(_) => (),
+            StepResult::EndOfProgram | StepResult::Halted(_) => return Repair::NotNeeded,
+            StepResult::AlreadyVisited(_) | StepResult::OutOfBounds(_) => break
+        }
+
+        if let Some(flipped) = flipped {
+            let target = successors(pc, flipped)[0].unwrap();
+            if (0..reaches.len() as i64).contains(&target) && reaches[target as usize] {
+                repairs.push(pc);
+            }
+        }
+    }
+
+    match repairs.len() {
+        0 => Repair::Impossible,
+        1 => Repair::Unique(repairs[0]),
+        _ => Repair::Ambiguous(repairs)
+    }
+}
+
+/// Runs the program as is, then with each possible swap.
+fn simulate(program: &Program) -> Repair {
+    let mut program = Program::from_instructions(program.instructions.clone());
+    if program.run().is_ok() {
+        return Repair::NotNeeded;
+    }
+
+    let repairs = program.brute_force_repairs();
+    match repairs.len() {
+        0 => Repair::Impossible,
+        1 => Repair::Unique(repairs[0]),
+        _ => Repair::Ambiguous(repairs)
+    }
+}
+
+#[test]
+fn test_terminating() {
+    let program = Program::new("example1.txt");
+    let reaches = terminating(&program.instructions);
+
+    assert_eq!(reaches, vec![false, false, false, false, false, false, false, false, true, true]);
+}
+
+#[test]
+fn test_analyze_matches_brute_force() {
+    let mut program = Program::new("example1.txt");
+
+    assert_eq!(analyze(&program), Repair::Unique(7));
+    assert_eq!(program.brute_force_repairs(), vec![7]);
+}
+
+#[test]
+fn test_analyze_edge_cases() {
+    let parse = |source| Program::parse(source, &InstructionSet::handheld()).unwrap();
+
+    assert_eq!(analyze(&parse("")), Repair::NotNeeded);
+    assert_eq!(analyze(&parse("acc +1\nnop +0")), Repair::NotNeeded);
+    assert_eq!(analyze(&parse("acc +1\njmp -1\njmp -1")), Repair::Impossible);
+
+    let mut ambiguous = parse("nop +3\njmp +0\nacc +1");
+    assert_eq!(analyze(&ambiguous), Repair::Ambiguous(vec![0, 1]));
+    assert_eq!(ambiguous.brute_force_repairs(), vec![0, 1]);
+}
+
+#[test]
+fn test_analyze_conditional_jumps() {
+    let parse = |source| Program::parse(source, &InstructionSet::extended()).unwrap();
+
+    // The graph thinks `jz b +2` can reach the end, but b is always one by then
+    let mut program = parse("nop +2\njmp +0\nacc b +1\njz b +2\njmp +0");
+    assert_eq!(analyze(&program), Repair::Impossible);
+    assert_eq!(program.brute_force_repairs(), vec![]);
+
+    let mut program = parse("nop +2\njmp +0\njz b +2\njmp +0");
+    assert_eq!(analyze(&program), Repair::Ambiguous(vec![0, 1]));
+    assert_eq!(program.brute_force_repairs(), vec![0, 1]);
+
+    assert_eq!(analyze(&parse("jz b +2\njmp +0")), Repair::NotNeeded);
+
+    let program = parse("acc b +1\njz b +2\njmp -1");
+    assert_eq!(analyze(&program), Repair::Unique(2));
+}
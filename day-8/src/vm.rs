@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::SplitWhitespace;
+
+pub const REGISTER_COUNT: usize = 4;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Register {
+    A = 0,
+    B = 1,
+    C = 2,
+    D = 3
+}
+
+impl Register {
+    fn parse(token: &str) -> Option<Register> {
+        match token {
+            "a" => Some(Register::A),
+            "b" => Some(Register::B),
+            "c" => Some(Register::C),
+            "d" => Some(Register::D),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Instruction {
+    Acc(Register, i32),
+    Jmp(i32),
+    Nop(i32),
+    Mul(Register, i32),
+    Jz(Register, i32),
+    Out(Register),
+    Hlt
+}
+
+impl Instruction {
+    /// The instruction with `jmp` and `nop` swapped, if it is one of those.
+    pub fn flipped(&self) -> Option<Instruction> {
+        match *self {
+            Instruction::Jmp(x) => Some(Instruction::Nop(x)),
+            Instruction::Nop(x) => Some(Instruction::Jmp(x)),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Register::A => "a",
+            Register::B => "b",
+            Register::C => "c",
+            Register::D => "d"
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Acc(Register::A, x) => write!(f, "acc {:+}", x),
+            Instruction::Acc(r, x) => write!(f, "acc {} {:+}", r, x),
+            Instruction::Jmp(x) => write!(f, "jmp {:+}", x),
+            Instruction::Nop(x) => write!(f, "nop {:+}", x),
+            Instruction::Mul(Register::A, x) => write!(f, "mul {:+}", x),
+            Instruction::Mul(r, x) => write!(f, "mul {} {:+}", r, x),
+            Instruction::Jz(Register::A, x) => write!(f, "jz {:+}", x),
+            Instruction::Jz(r, x) => write!(f, "jz {} {:+}", r, x),
+            Instruction::Out(Register::A) => write!(f, "out"),
+            Instruction::Out(r) => write!(f, "out {}", r),
+            Instruction::Hlt => write!(f, "hlt")
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    Empty,
+    UnknownOpcode(String),
+    MissingOperand(String),
+    InvalidOperand(String),
+    UnknownRegister(String),
+    UnexpectedOperand(String)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ProgramError {
+    pub line: usize,
+    pub error: DecodeError
+}
+
+pub struct Operands<'a> {
+    opcode: &'a str,
+    tokens: Peekable<SplitWhitespace<'a>>
+}
+
+impl<'a> Operands<'a> {
+    /// An optional register name; instructions without one operate on `a`.
+    pub fn register(&mut self) -> Result<Register, DecodeError> {
+        match self.tokens.peek() {
+            Some(token) if token.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                let token = self.tokens.next().unwrap();
+                Register::parse(token).ok_or_else(|| DecodeError::UnknownRegister(token.to_string()))
+            },
+            _ => Ok(Register::A)
+        }
+    }
+
+    pub fn value(&mut self) -> Result<i32, DecodeError> {
+        let token = self.tokens.next()
+            .ok_or_else(|| DecodeError::MissingOperand(self.opcode.to_string()))?;
+
+        token.parse().map_err(|_| DecodeError::InvalidOperand(token.to_string()))
+    }
+
+    fn finish(mut self) -> Result<(), DecodeError> {
+        match self.tokens.next() {
+            Some(token) => Err(DecodeError::UnexpectedOperand(token.to_string())),
+            None => Ok(())
+        }
+    }
+}
+
+pub type Decoder = fn(&mut Operands) -> Result<Instruction, DecodeError>;
+
+pub struct InstructionSet {
+    decoders: HashMap<&'static str, Decoder>
+}
+
+impl InstructionSet {
+    pub fn empty() -> Self {
+        InstructionSet { decoders: HashMap::new() }
+    }
+
+    /// The three instructions of the original handheld console.
+    pub fn handheld() -> Self {
+        let mut set = InstructionSet::empty();
+        set.register("acc", |ops| Ok(Instruction::Acc(ops.register()?, ops.value()?)))
+            .register("jmp", |ops| Ok(Instruction::Jmp(ops.value()?)))
+            .register("nop", |ops| Ok(Instruction::Nop(ops.value()?)));
+        set
+    }
+
+    pub fn extended() -> Self {
+        let mut set = InstructionSet::handheld();
+        set.register("mul", |ops| Ok(Instruction::Mul(ops.register()?, ops.value()?)))
+            .register("jz", |ops| Ok(Instruction::Jz(ops.register()?, ops.value()?)))
+            .register("out", |ops| Ok(Instruction::Out(ops.register()?)))
+            .register("hlt", |_| Ok(Instruction::Hlt));
+        set
+    }
+
+    pub fn register(&mut self, opcode: &'static str, decoder: Decoder) -> &mut Self {
+        self.decoders.insert(opcode, decoder);
+        self
+    }
+
+    pub fn decode(&self, line: &str) -> Result<Instruction, DecodeError> {
+        let mut tokens = line.split_whitespace().peekable();
+        let opcode = tokens.next().ok_or(DecodeError::Empty)?;
+        let decoder = self.decoders.get(opcode)
+            .ok_or_else(|| DecodeError::UnknownOpcode(opcode.to_string()))?;
+
+        let mut operands = Operands { opcode, tokens };
+        let instruction = decoder(&mut operands)?;
+        operands.finish()?;
+
+        Ok(instruction)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum StepResult {
+    Ok { from: usize, to: usize },
+    Output(i32),
+    Halted(usize),
+    EndOfProgram,
+    AlreadyVisited(usize),
+    OutOfBounds(i64)
+}
+
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+    pub registers: [i32; REGISTER_COUNT],
+    pub pc: usize,
+    pub output: Vec<i32>,
+    visited: Vec<bool>
+}
+
+impl Program {
+    pub fn new(path: &str) -> Self {
+        let source = std::fs::read_to_string(path).unwrap();
+        Program::parse(&source, &InstructionSet::extended()).unwrap()
+    }
+
+    pub fn parse(source: &str, set: &InstructionSet) -> Result<Self, ProgramError> {
+        let instructions = source.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| set.decode(line).map_err(|error| ProgramError { line: index + 1, error }))
+            .collect::<Result<Vec<Instruction>, ProgramError>>()?;
+
+        Ok(Program::from_instructions(instructions))
+    }
+
+    pub fn from_instructions(instructions: Vec<Instruction>) -> Self {
+        let visited = vec![false; instructions.len()];
+        Program { instructions, registers: [0; REGISTER_COUNT], pc: 0, output: Vec::new(), visited }
+    }
+
+    pub fn acc(&self) -> i32 {
+        self.register(Register::A)
+    }
+
+    pub fn register(&self, register: Register) -> i32 {
+        self.registers[register as usize]
+    }
+
+    pub fn reset(&mut self) {
+        self.visited = vec![false; self.instructions.len()];
+        self.registers = [0; REGISTER_COUNT];
+        self.output = Vec::new();
+        self.pc = 0;
+    }
+
+    pub fn run(&mut self) -> Result<(), StepResult> {
+        self.reset();
+
+        loop {
+            match self.step() {
+                StepResult::Ok { .. } | StepResult::Output(_) => (),
+                StepResult::EndOfProgram | StepResult::Halted(_) => return Ok(()),
+                failure => return Err(failure)
+            }
+        }
+    }
+
+    pub fn step(&mut self) -> StepResult {
+        if self.pc >= self.instructions.len() {
+            return StepResult::EndOfProgram;
+        }
+
+        if self.visited[self.pc] {
+            return StepResult::AlreadyVisited(self.pc);
+        }
+
+        let from = self.pc;
+        let mut output = None;
+        self.visited[from] = true;
+
+        let offset = match self.instructions[from] {
+            Instruction::Nop(_) => 1,
+            Instruction::Acc(r, x) => { self.registers[r as usize] += x; 1 },
+            Instruction::Mul(r, x) => { self.registers[r as usize] *= x; 1 },
+            Instruction::Jmp(x) => x,
+            Instruction::Jz(r, x) => if self.registers[r as usize] == 0 { x } else { 1 },
+            Instruction::Out(r) => {
+                output = Some(self.registers[r as usize]);
+                self.output.push(self.registers[r as usize]);
+                1
+            },
+            Instruction::Hlt => return StepResult::Halted(from)
+        };
+
+        let to = from as i64 + offset as i64;
+        if to < 0 || to > self.instructions.len() as i64 {
+            return StepResult::OutOfBounds(to);
+        }
+
+        self.pc = to as usize;
+        if self.pc == self.instructions.len() {
+            return StepResult::EndOfProgram;
+        }
+
+        match output {
+            Some(value) => StepResult::Output(value),
+            None => StepResult::Ok { from, to: self.pc }
+        }
+    }
+
+    pub fn current_instruction(&self) -> Option<Instruction> {
+        self.instructions.get(self.pc).copied()
+    }
+
+    pub fn flip(&mut self, index: usize) {
+        if let Some(flipped) = self.instructions[index].flipped() {
+            self.instructions[index] = flipped;
+        }
+    }
+
+    #[cfg(test)]
+    pub fn attempt_correction(&mut self) -> bool {
+        for index in 0..self.instructions.len() {
+            if self.instructions[index].flipped().is_none() { continue; }
+
+            // Perform the swap
+            self.flip(index);
+
+            // Run the program and return true if a good swap was found
+            if self.run().is_ok() { return true; }
+
+            // Otherwise revert the swap and continue
+            self.flip(index);
+        }
+
+        false
+    }
+
+    /// Every index whose swap makes the program terminate, found by re-running it for each candidate.
+    pub fn brute_force_repairs(&mut self) -> Vec<usize> {
+        let mut repairs = Vec::new();
+
+        for index in 0..self.instructions.len() {
+            if self.instructions[index].flipped().is_none() { continue; }
+
+            self.flip(index);
+            if self.run().is_ok() { repairs.push(index); }
+            self.flip(index);
+        }
+
+        self.reset();
+        repairs
+    }
+}
+
+#[test]
+fn test_register_operands() {
+    let set = InstructionSet::extended();
+
+    assert_eq!(set.decode("acc b -3"), Ok(Instruction::Acc(Register::B, -3)));
+    assert_eq!(set.decode("jz c +2"), Ok(Instruction::Jz(Register::C, 2)));
+    assert_eq!(set.decode("out"), Ok(Instruction::Out(Register::A)));
+    assert_eq!(set.decode("hlt"), Ok(Instruction::Hlt));
+}
+
+#[test]
+fn test_decode_errors() {
+    let set = InstructionSet::extended();
+
+    assert_eq!(set.decode("foo +1"), Err(DecodeError::UnknownOpcode("foo".to_string())));
+    assert_eq!(set.decode("jmp"), Err(DecodeError::MissingOperand("jmp".to_string())));
+    assert_eq!(set.decode("acc +x"), Err(DecodeError::InvalidOperand("+x".to_string())));
+    assert_eq!(set.decode("acc z +1"), Err(DecodeError::UnknownRegister("z".to_string())));
+    assert_eq!(set.decode("hlt +1"), Err(DecodeError::UnexpectedOperand("+1".to_string())));
+    assert_eq!(InstructionSet::handheld().decode("mul +2"), Err(DecodeError::UnknownOpcode("mul".to_string())));
+
+    let error = Program::parse("nop +0\nbad +1", &set).err().unwrap();
+    assert_eq!(error, ProgramError { line: 2, error: DecodeError::UnknownOpcode("bad".to_string()) });
+}
+
+#[test]
+fn test_extended_program() {
+    let source = "acc +2\nmul +3\njz b +2\nacc +100\nout\nhlt\nacc +1";
+    let mut program = Program::parse(source, &InstructionSet::extended()).unwrap();
+
+    assert_eq!(program.step(), StepResult::Ok { from: 0, to: 1 });
+    assert_eq!(program.run(), Ok(()));
+    assert_eq!(program.acc(), 6);
+    assert_eq!(program.output, vec![6]);
+    assert_eq!(program.pc, 5);
+}
+
+#[test]
+fn test_empty_program() {
+    let mut program = Program::parse("", &InstructionSet::extended()).unwrap();
+    assert_eq!(program.run(), Ok(()));
+    assert_eq!(program.step(), StepResult::EndOfProgram);
+
+    let mut program = Program::parse("acc +1", &InstructionSet::extended()).unwrap();
+    assert_eq!(program.step(), StepResult::EndOfProgram);
+    assert_eq!(program.step(), StepResult::EndOfProgram);
+}
+
+#[test]
+fn test_instruction_display() {
+    let set = InstructionSet::extended();
+
+    for line in &["acc +1", "acc b -3", "jmp -4", "nop +0", "mul c +2", "jz +3", "out d", "hlt"] {
+        assert_eq!(set.decode(line).unwrap().to_string(), *line);
+    }
+}
@@ -1,3 +1,7 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
 fn read_input(path: &str) -> Vec<u64> {
     std::fs::read_to_string(path)
         .unwrap()
@@ -6,80 +10,199 @@ fn read_input(path: &str) -> Vec<u64> {
         .collect()
 }
 
-fn is_valid(index: usize, numbers: &Vec<u64>, look_behind_amount: usize) -> bool {
-    let possibilities = &numbers[(index - look_behind_amount)..index];
-    let target = numbers[index];
+#[derive(Debug, Eq, PartialEq)]
+struct InvalidNumber {
+    index: usize,
+    number: u64
+}
+
+/// Checks numbers one at a time against the sums of every pair in the preceding window.
+/// The window's pair sums are kept as a multiset so each number costs O(preamble) to add.
+struct XmasValidator {
+    preamble: usize,
+    window: VecDeque<u64>,
+    sums: HashMap<u64, usize>,
+    index: usize
+}
+
+impl XmasValidator {
+    fn new(preamble: usize) -> Self {
+        assert!(preamble >= 2, "a number needs at least two earlier numbers to be the sum of a pair");
+        XmasValidator { preamble, window: VecDeque::with_capacity(preamble), sums: HashMap::new(), index: 0 }
+    }
 
-    for lower_index in 0..(possibilities.len() - 1) {
-        for upper_index in (1 + lower_index)..possibilities.len() {
-            if possibilities[lower_index] + possibilities[upper_index] == target {
-                return true;
+    /// Feeds the next number, returning it as invalid if no pair in the window sums to it.
+    fn push(&mut self, number: u64) -> Option<InvalidNumber> {
+        let mut invalid = None;
+
+        if self.window.len() == self.preamble {
+            if !self.sums.contains_key(&number) {
+                invalid = Some(InvalidNumber { index: self.index, number });
+            }
+
+            if let Some(oldest) = self.window.pop_front() {
+                for other in &self.window {
+                    let count = self.sums.get_mut(&(oldest + other)).unwrap();
+                    *count -= 1;
+                    if *count == 0 { self.sums.remove(&(oldest + other)); }
+                }
             }
         }
+
+        for other in &self.window {
+            *self.sums.entry(number + other).or_insert(0) += 1;
+        }
+
+        self.window.push_back(number);
+        self.index += 1;
+        invalid
     }
+}
+
+fn find_invalid<R: BufRead>(reader: R, preamble: usize) -> io::Result<Vec<InvalidNumber>> {
+    let mut validator = XmasValidator::new(preamble);
+    let mut invalid = Vec::new();
 
-    false
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+
+        let number = line.trim().parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("not a number: {}", line)))?;
+
+        invalid.extend(validator.push(number));
+    }
+
+    Ok(invalid)
 }
 
-fn find_continuous_sum(numbers: &Vec<u64>, target: u64) -> Option<(usize, usize)> {
-    for index in 0..numbers.len() {
-        let mut sum = numbers[index];
-        let mut inner_index = index + 1;
+/// Every range `(start, length)` of at least `min_length` numbers summing to `target`.
+///
+/// Since the numbers are never negative a single window can slide across them: the end only ever
+/// moves forward and each start is visited once. Trailing zeros give additional matching ranges.
+fn continuous_sums(numbers: &[u64], target: u64, min_length: usize) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut end = 0;
+    let mut sum = 0;
+
+    for start in 0..numbers.len() {
+        if end < start {
+            end = start;
+            sum = 0;
+        }
 
-        while sum < target {
-            sum += numbers[inner_index];
-            inner_index += 1;
+        while end < numbers.len() && sum < target {
+            sum += numbers[end];
+            end += 1;
         }
 
         if sum == target {
-            return Some((index, inner_index - index));
-        }       
+            let mut extended = end;
+            loop {
+                let length = extended - start;
+                if length > 0 && length >= min_length {
+                    ranges.push((start, length));
+                }
+
+                if extended < numbers.len() && numbers[extended] == 0 { extended += 1; } else { break; }
+            }
+        }
+
+        if end > start {
+            sum -= numbers[start];
+        }
     }
 
-    None
+    ranges
+}
+
+fn find_continuous_sum(numbers: &[u64], target: u64, min_length: usize) -> Option<(usize, usize)> {
+    continuous_sums(numbers, target, min_length).into_iter().next()
 }
 
 fn sum_of_extremes(numbers: &[u64]) -> u64 {
     numbers.iter().min().unwrap() + numbers.iter().max().unwrap()
 }
 
+const USAGE: &str = "usage: day-9 [preamble of at least 2] [input]";
+
 fn main() {
-    let numbers = read_input("input.txt");
+    let args: Vec<String> = std::env::args().collect();
+    let preamble = match args.get(1).map(|p| p.parse::<usize>()) {
+        None => 25,
+        Some(Ok(preamble)) if preamble >= 2 => preamble,
+        Some(_) => return eprintln!("invalid preamble {}\n{}", args[1], USAGE)
+    };
+    let path = args.get(2).map(|p| p.as_str()).unwrap_or("input.txt");
 
     // Part 1
-    let invalid_number = (25..numbers.len())
-        .find(|x| !is_valid(*x, &numbers, 25))
-        .unwrap();
-
-    println!("The first invalid number is: {}", numbers[invalid_number]);
+    let invalid = find_invalid(BufReader::new(File::open(path).unwrap()), preamble).unwrap();
+    let first = match invalid.first() {
+        Some(first) => first.number,
+        None => return println!("Every number is valid")
+    };
+
+    println!("The first invalid number is: {}", first);
+    for other in invalid.iter().skip(1) {
+        println!("Also invalid: {} at index {}", other.number, other.index);
+    }
 
     // Part 2
-    let (index, length) = find_continuous_sum(&numbers, numbers[invalid_number]).unwrap();
-    let slice = &numbers[index..index + length];
-
-    println!("The sum of the highest and lowest number in a continuous slice that add to that are: {}", sum_of_extremes(&slice));
-
+    let numbers = read_input(path);
+    match find_continuous_sum(&numbers, first, 2) {
+        Some((index, length)) => {
+            let slice = &numbers[index..index + length];
+            println!("The sum of the highest and lowest number in a continuous slice that add to that are: {}", sum_of_extremes(slice));
+        },
+        None => println!("No continuous slice adds up to {}", first)
+    }
 }
 
 #[test]
 fn test_find_invalid() {
-    let numbers = read_input("example.txt");
-    let invalid_numbers: Vec<usize> = (5..numbers.len())
-        .filter(|x| !is_valid(*x, &numbers, 5))
-        .collect();
+    let invalid_numbers = find_invalid(BufReader::new(File::open("example.txt").unwrap()), 5).unwrap();
 
-    assert_eq!(invalid_numbers.len(), 1);
-    assert_eq!(numbers[invalid_numbers[0]], 127);
+    assert_eq!(invalid_numbers, vec![InvalidNumber { index: 14, number: 127 }]);
+}
+
+#[test]
+fn test_find_every_invalid() {
+    let input = "1\n2\n3\n3\n7\n10\n17\n20\n";
+    let invalid_numbers = find_invalid(input.as_bytes(), 2).unwrap();
+
+    assert_eq!(invalid_numbers, vec![
+        InvalidNumber { index: 3, number: 3 },
+        InvalidNumber { index: 4, number: 7 },
+        InvalidNumber { index: 7, number: 20 }
+    ]);
+    assert!(find_invalid("1\nx\n".as_bytes(), 2).is_err());
+    assert_eq!(find_invalid("1\n2\n3\n".as_bytes(), 2).unwrap(), vec![]);
 }
 
 #[test]
 fn test_sum_to_invalid() {
     let numbers = read_input("example.txt");
-    let (index, length) = find_continuous_sum(&numbers, 127).unwrap();
+    let (index, length) = find_continuous_sum(&numbers, 127, 2).unwrap();
 
     assert_eq!(index, 2);
     assert_eq!(length, 4);
 
     let slice = &numbers[index..index + length];
-    assert_eq!(sum_of_extremes(&slice), 62);
+    assert_eq!(sum_of_extremes(slice), 62);
+}
+
+#[test]
+fn test_continuous_sum_without_solution() {
+    assert_eq!(find_continuous_sum(&[1, 2, 3], 100, 1), None);
+    assert_eq!(find_continuous_sum(&[5, 10, 20], 15, 3), None);
+    assert_eq!(find_continuous_sum(&[], 1, 1), None);
+}
+
+#[test]
+fn test_all_continuous_sums() {
+    let numbers = read_input("example.txt");
+
+    assert_eq!(continuous_sums(&numbers, 127, 1), vec![(2, 4), (14, 1)]);
+    assert_eq!(continuous_sums(&numbers, 127, 2), vec![(2, 4)]);
+    assert_eq!(continuous_sums(&[3, 0, 2, 1, 0, 3], 3, 1), vec![(0, 1), (0, 2), (1, 3), (1, 4), (2, 2), (2, 3), (4, 2), (5, 1)]);
 }